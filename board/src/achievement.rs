@@ -0,0 +1,229 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// What unlocks an achievement, checked against session events
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// Clear a board within limits, `None` meaning no limit
+    ClearBoard {
+        max_hints: Option<usize>,
+        /// Most direction changes of any connection, 0 for straight lines
+        /// only
+        max_turns: Option<usize>,
+        /// Seconds of game clock
+        max_time: Option<f64>,
+    },
+    /// Reach a combo of this many consecutive matches
+    Combo(usize),
+    /// Make this many matches in one game
+    Matches(usize),
+    FinishCampaign,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Achievement {
+    /// Stable name used in saves
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+impl Achievement {
+    pub fn new(id: &str, name: &str, description: &str, condition: Condition) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            condition,
+        }
+    }
+}
+
+/// Built-in achievements
+pub fn default_achievements() -> Vec<Achievement> {
+    vec![
+        Achievement::new(
+            "first_clear",
+            "Clean slate",
+            "Clear a board",
+            Condition::ClearBoard {
+                max_hints: None,
+                max_turns: None,
+                max_time: None,
+            },
+        ),
+        Achievement::new(
+            "no_hints",
+            "Sharp eyes",
+            "Clear a board without hints",
+            Condition::ClearBoard {
+                max_hints: Some(0),
+                max_turns: None,
+                max_time: None,
+            },
+        ),
+        Achievement::new(
+            "straight_only",
+            "Straight shooter",
+            "Clear a board using only straight connections",
+            Condition::ClearBoard {
+                max_hints: None,
+                max_turns: Some(0),
+                max_time: None,
+            },
+        ),
+        Achievement::new(
+            "speed_clear",
+            "Speedrunner",
+            "Clear a board in under a minute",
+            Condition::ClearBoard {
+                max_hints: None,
+                max_turns: None,
+                max_time: Some(60.),
+            },
+        ),
+        Achievement::new(
+            "combo_10",
+            "On fire",
+            "Make a 10-match combo",
+            Condition::Combo(10),
+        ),
+        Achievement::new(
+            "matches_50",
+            "Marathon",
+            "Make 50 matches in one game",
+            Condition::Matches(50),
+        ),
+        Achievement::new(
+            "campaign",
+            "Meme lord",
+            "Finish the campaign",
+            Condition::FinishCampaign,
+        ),
+    ]
+}
+
+/// Ids of unlocked achievements, kept by player
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unlocked {
+    /// In order of unlocking
+    pub ids: Vec<String>,
+}
+
+impl Unlocked {
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.iter().any(|unlocked| unlocked == id)
+    }
+}
+
+/// One id per line
+impl fmt::Display for Unlocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in self.ids.iter() {
+            writeln!(f, "{}", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Unlocked {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            ids: s
+                .lines()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+}
+
+/// What happened so far in current game
+#[derive(Debug, Clone, Default)]
+struct GameProgress {
+    hints: usize,
+    max_turns: usize,
+    matches: usize,
+    /// Game clock of latest match
+    clock: f64,
+}
+
+/// Follow session events and unlock achievements whose condition is met.
+/// Per-game progress restarts once a game is over.
+#[derive(Debug, Clone)]
+pub struct AchievementTracker {
+    achievements: Vec<Achievement>,
+    pub unlocked: Unlocked,
+    game: GameProgress,
+}
+
+impl AchievementTracker {
+    pub fn new(achievements: Vec<Achievement>, unlocked: Unlocked) -> Self {
+        Self {
+            achievements,
+            unlocked,
+            game: Default::default(),
+        }
+    }
+
+    pub fn achievements(&self) -> &[Achievement] {
+        &self.achievements
+    }
+
+    fn is_met(&self, condition: &Condition, event: &Event) -> bool {
+        let game = &self.game;
+        let within = |limit: &Option<usize>, value: usize| limit.is_none_or(|limit| value <= limit);
+        match (condition, event) {
+            (
+                Condition::ClearBoard {
+                    max_hints,
+                    max_turns,
+                    max_time,
+                },
+                Event::BoardCleared,
+            ) => {
+                within(max_hints, game.hints)
+                    && within(max_turns, game.max_turns)
+                    && max_time.is_none_or(|limit| game.clock <= limit)
+            }
+            // Combo counts matches after the first one
+            (Condition::Combo(combo), Event::Score(change)) => change.combo + 1 >= *combo,
+            (Condition::Matches(matches), Event::Matched(_)) => game.matches >= *matches,
+            (Condition::FinishCampaign, Event::CampaignFinished) => true,
+            _ => false,
+        }
+    }
+
+    /// Follow `event`, return achievements it unlocked
+    pub fn record(&mut self, event: &Event) -> Vec<Achievement> {
+        match event {
+            Event::Matched(couple) => {
+                self.game.matches += 1;
+                self.game.max_turns = self.game.max_turns.max(couple.turns());
+                self.game.clock = couple.epoch;
+            }
+            Event::Hinted(_) => self.game.hints += 1,
+            _ => {}
+        }
+
+        let unlocked: Vec<Achievement> = self
+            .achievements
+            .iter()
+            .filter(|achievement| !self.unlocked.contains(&achievement.id))
+            .filter(|achievement| self.is_met(&achievement.condition, event))
+            .cloned()
+            .collect();
+        self.unlocked
+            .ids
+            .extend(unlocked.iter().map(|achievement| achievement.id.clone()));
+
+        if let Event::StateChanged(State::Won | State::Lost) = event {
+            self.game = Default::default();
+        }
+        unlocked
+    }
+}
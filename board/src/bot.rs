@@ -0,0 +1,202 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// How a bot picks among available moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Any available move
+    Random,
+    /// Move leaving the most moves available right after
+    Greedy,
+    /// Move leaving the most moves available `depth` matches ahead
+    Solver { depth: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    /// Seconds between moves, before jitter
+    pub fn reaction_time(&self) -> f64 {
+        match self {
+            Self::Easy => 2.,
+            Self::Normal => 1.2,
+            Self::Hard => 0.6,
+        }
+    }
+
+    /// Chance of selecting a group that doesn't match
+    pub fn mistake_chance(&self) -> f64 {
+        match self {
+            Self::Easy => 0.2,
+            Self::Normal => 0.08,
+            Self::Hard => 0.02,
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        match self {
+            Self::Easy => Strategy::Random,
+            Self::Normal => Strategy::Greedy,
+            Self::Hard => Strategy::Solver { depth: 2 },
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.to_string() == s)
+            .ok_or("unknown difficulty")
+    }
+}
+
+/// Computer player, producing the same actions as a human would. Feed it the
+/// session it plays every update, only while it is its turn.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+    rng: Rng,
+    /// Countdown until next move
+    wait: f64,
+}
+
+impl Bot {
+    /// Candidate moves explored on each level of lookahead
+    const BRANCHING: usize = 6;
+
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        let mut bot = Self {
+            difficulty,
+            rng: Rng::new(seed),
+            wait: 0.,
+        };
+        bot.wait = bot.reaction_time();
+        bot
+    }
+
+    /// Reaction time with up to 30% jitter either way
+    fn reaction_time(&mut self) -> f64 {
+        let jitter = self.rng.gen_range(0, 61) as f64 / 100. - 0.3;
+        self.difficulty.reaction_time() * (1. + jitter)
+    }
+
+    fn roll(&mut self, chance: f64) -> bool {
+        (self.rng.gen_range(0, 1000) as f64) < chance * 1000.
+    }
+
+    /// Actions to apply on `session` in an update of `dt` seconds
+    pub fn update(&mut self, session: &GameSession, dt: f64) -> Vec<Action> {
+        if !matches!(session.state(), State::Playing | State::Stuck) {
+            return Vec::new();
+        }
+        self.wait -= dt;
+        if self.wait > 0. {
+            return Vec::new();
+        }
+        self.wait = self.reaction_time();
+
+        if session.state() == State::Stuck {
+            return match session.shuffles_left() {
+                Some(0) => Vec::new(),
+                _ => vec![Action::Shuffle],
+            };
+        }
+        let group = if self.roll(self.difficulty.mistake_chance()) {
+            self.mistake(session)
+        } else {
+            self.choose(session)
+        };
+        group.into_iter().map(Action::Select).collect()
+    }
+
+    /// Random tiles, most likely not a group
+    fn mistake(&mut self, session: &GameSession) -> Vec<Cell> {
+        let mapping = session.mapping();
+        let mut tiles: Vec<Cell> = mapping
+            .mutable_cells()
+            .filter(|cell| mapping.get_sprite(cell) != NO_SPRITE)
+            .collect();
+        let mut group = Vec::new();
+        while group.len() < session.rules().group_size && !tiles.is_empty() {
+            group.push(tiles.swap_remove(self.rng.gen_range(0, tiles.len())));
+        }
+        group
+    }
+
+    fn choose(&mut self, session: &GameSession) -> Vec<Cell> {
+        let connector = session.connector();
+        let mapping = session.mapping();
+        let mut moves = connector.available_moves(mapping);
+        if moves.is_empty() {
+            return Vec::new();
+        }
+        let depth = match self.difficulty.strategy() {
+            Strategy::Random => {
+                let idx = self.rng.gen_range(0, moves.len());
+                return moves.swap_remove(idx);
+            }
+            Strategy::Greedy => 0,
+            Strategy::Solver { depth } => depth,
+        };
+        // Shuffle first so equally good moves are picked at random
+        for idx in (1..moves.len()).rev() {
+            moves.swap(idx, self.rng.gen_range(0, idx + 1));
+        }
+        moves.truncate(Self::BRANCHING);
+        moves
+            .into_iter()
+            .map(|group| {
+                let value = Self::evaluate(connector, &Self::after(mapping, &group), depth);
+                (value, group)
+            })
+            .max_by_key(|(value, _)| *value)
+            .map(|(_, group)| group)
+            .unwrap_or_default()
+    }
+
+    /// Board once `group` is cleared, ignoring special effects and gravity
+    fn after(mapping: &Mapping, group: &[Cell]) -> Mapping {
+        let mut mapping = mapping.clone();
+        group.iter().for_each(|cell| mapping.clear_cell(cell));
+        mapping
+    }
+
+    /// Moves available on `mapping` after `depth` more matches, clearing the
+    /// board beats everything
+    fn evaluate(connector: &CellConnector, mapping: &Mapping, depth: usize) -> usize {
+        if mapping.is_cleared() {
+            return usize::MAX;
+        }
+        let moves = connector.available_moves(mapping);
+        if depth == 0 || moves.is_empty() {
+            return moves.len();
+        }
+        moves
+            .iter()
+            .take(Self::BRANCHING)
+            .map(|group| Self::evaluate(connector, &Self::after(mapping, group), depth - 1))
+            .max()
+            .unwrap_or(0)
+    }
+}
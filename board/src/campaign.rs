@@ -0,0 +1,254 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub rules: Rules,
+}
+
+impl Level {
+    pub fn new(name: &str, rules: Rules) -> Self {
+        Self {
+            name: name.to_string(),
+            rules,
+        }
+    }
+}
+
+/// Built-in levels, from small boards to full size with gravity and timer
+pub fn default_levels() -> Vec<Level> {
+    vec![
+        Level::new(
+            "Warm up",
+            Rules {
+                columns: 8,
+                rows: 6,
+                tiles: 24,
+                sprite_pool: (1..=6).collect(),
+                time_limit: Some(120.),
+                ..Default::default()
+            },
+        ),
+        Level::new(
+            "Falling memes",
+            Rules {
+                columns: 10,
+                rows: 8,
+                tiles: 48,
+                sprite_pool: (7..=18).collect(),
+                gravity: Some(Direction::Down),
+                time_limit: Some(150.),
+                shuffles: Some(3),
+                ..Default::default()
+            },
+        ),
+        Level::new(
+            "Drifting left",
+            Rules {
+                columns: 12,
+                rows: 10,
+                tiles: 72,
+                sprite_pool: (19..=36).collect(),
+                gravity: Some(Direction::Left),
+                time_limit: Some(180.),
+                shuffles: Some(2),
+                ..Default::default()
+            },
+        ),
+        Level::new(
+            "Full board",
+            Rules {
+                columns: 16,
+                rows: 12,
+                tiles: 128,
+                sprite_pool: (37..=68).collect(),
+                time_limit: Some(240.),
+                shuffles: Some(2),
+                ..Default::default()
+            },
+        ),
+        Level::new(
+            "Gravity storm",
+            Rules {
+                columns: 16,
+                rows: 16,
+                tiles: 160,
+                sprite_pool: (69..=108).collect(),
+                gravity: Some(Direction::Up),
+                time_limit: Some(300.),
+                shuffles: Some(1),
+                ..Default::default()
+            },
+        ),
+    ]
+}
+
+/// Where player is in campaign, saved between launches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Index of current level
+    pub level: usize,
+    /// Score of completed levels
+    pub score: i64,
+    pub lives: usize,
+    /// Seed of whole campaign, each level derives its own
+    pub seed: u64,
+}
+
+impl Progress {
+    pub fn new(seed: u64, lives: usize) -> Self {
+        Self {
+            level: 0,
+            score: 0,
+            lives,
+            seed,
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "level={}", self.level)?;
+        writeln!(f, "score={}", self.score)?;
+        writeln!(f, "lives={}", self.lives)?;
+        writeln!(f, "seed={}", self.seed)
+    }
+}
+
+impl FromStr for Progress {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut progress = Self::new(0, 0);
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            let value = value.trim();
+            match key.trim() {
+                "level" => progress.level = value.parse().map_err(|_| "invalid level")?,
+                "score" => progress.score = value.parse().map_err(|_| "invalid score")?,
+                "lives" => progress.lives = value.parse().map_err(|_| "invalid lives")?,
+                "seed" => progress.seed = value.parse().map_err(|_| "invalid seed")?,
+                _ => return Err("unknown key"),
+            }
+        }
+        Ok(progress)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Playing,
+    /// Level cleared, waiting to go to next level
+    LevelComplete,
+    /// Level lost with lives left, waiting to retry
+    LevelFailed,
+    /// No life left
+    GameOver,
+    /// All levels cleared
+    Finished,
+}
+
+/// Sequence of levels sharing score and lives
+pub struct Campaign {
+    levels: Vec<Level>,
+    progress: Progress,
+    session: GameSession,
+    stage: Stage,
+}
+
+impl Campaign {
+    pub fn new(levels: Vec<Level>, progress: Progress) -> Result<Self, &'static str> {
+        if levels.is_empty() {
+            return Err("campaign has no level");
+        }
+        let stage = if progress.level >= levels.len() {
+            Stage::Finished
+        } else if progress.lives == 0 {
+            Stage::GameOver
+        } else {
+            Stage::Playing
+        };
+        let level = progress.level.min(levels.len() - 1);
+        let session = Self::level_session(&levels[level], &progress, level);
+        Ok(Self {
+            levels,
+            progress,
+            session,
+            stage,
+        })
+    }
+
+    fn level_session(level: &Level, progress: &Progress, idx: usize) -> GameSession {
+        let seed = Rng::new(progress.seed ^ idx as u64).next_u64();
+        GameSession::new(level.rules.clone(), seed)
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+
+    /// Current level, last one when finished
+    pub fn level(&self) -> &Level {
+        &self.levels[self.progress.level.min(self.levels.len() - 1)]
+    }
+
+    pub fn session(&self) -> &GameSession {
+        &self.session
+    }
+
+    /// Score of completed levels plus current one while playing
+    pub fn total_score(&self) -> i64 {
+        match self.stage {
+            Stage::Playing => self.progress.score + self.session.scoring().total(),
+            _ => self.progress.score,
+        }
+    }
+
+    /// Drive current level, return its events
+    pub fn update(&mut self, input: &[Action], dt: f64) -> Vec<Event> {
+        if self.stage != Stage::Playing {
+            return Vec::new();
+        }
+        let mut events = self.session.update(input, dt);
+        match self.session.state() {
+            State::Won => {
+                self.progress.score += self.session.scoring().total();
+                self.progress.level += 1;
+                self.stage = if self.progress.level >= self.levels.len() {
+                    events.push(Event::CampaignFinished);
+                    Stage::Finished
+                } else {
+                    Stage::LevelComplete
+                };
+            }
+            State::Lost => {
+                self.progress.lives = self.progress.lives.saturating_sub(1);
+                self.stage = if self.progress.lives == 0 {
+                    Stage::GameOver
+                } else {
+                    Stage::LevelFailed
+                };
+            }
+            _ => {}
+        }
+        events
+    }
+
+    /// Leave level complete or failed screen, start next level or retry
+    pub fn advance(&mut self) {
+        if matches!(self.stage, Stage::LevelComplete | Stage::LevelFailed) {
+            let idx = self.progress.level;
+            self.session = Self::level_session(&self.levels[idx], &self.progress, idx);
+            self.stage = Stage::Playing;
+        }
+    }
+}
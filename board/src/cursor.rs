@@ -0,0 +1,72 @@
+use crate::prelude::*;
+
+/// Board cursor for keyboard play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub cell: Cell,
+    /// Jump over empty cells to the nearest tile
+    pub skip_empty: bool,
+}
+
+impl Cursor {
+    /// Cursor at the first tile of board, or at top-left cell if board is
+    /// empty
+    pub fn new(mapping: &Mapping, skip_empty: bool) -> Self {
+        let cell = mapping
+            .mutable_cells()
+            .find(|cell| mapping.get_sprite(cell) != NO_SPRITE)
+            .unwrap_or(Cell { column: 1, row: 1 });
+        Self { cell, skip_empty }
+    }
+
+    /// Move cursor one step toward `direction`, staying inside playable area.
+    /// When skipping empty cells, go to the nearest tile on that side instead,
+    /// preferring tiles on the same line.
+    pub fn step(&mut self, mapping: &Mapping, direction: Direction) {
+        let current = self.cell;
+        if !self.skip_empty {
+            let next = current.neighbour(&direction);
+            if mapping.mutable_cells().any(|cell| cell == next) {
+                self.cell = next;
+            }
+            return;
+        }
+
+        // (distance along direction, distance off axis)
+        let offset = |cell: &Cell| match direction {
+            Direction::Up => (
+                current.row as isize - cell.row as isize,
+                cell.column.abs_diff(current.column),
+            ),
+            Direction::Down => (
+                cell.row as isize - current.row as isize,
+                cell.column.abs_diff(current.column),
+            ),
+            Direction::Left => (
+                current.column as isize - cell.column as isize,
+                cell.row.abs_diff(current.row),
+            ),
+            Direction::Right => (
+                cell.column as isize - current.column as isize,
+                cell.row.abs_diff(current.row),
+            ),
+        };
+        if let Some(cell) = mapping
+            .mutable_cells()
+            .filter(|cell| mapping.get_sprite(cell) != NO_SPRITE)
+            .filter(|cell| offset(cell).0 > 0)
+            .min_by_key(|cell| {
+                let (along, off_axis) = offset(cell);
+                (off_axis, along)
+            })
+        {
+            self.cell = cell;
+        }
+    }
+
+    /// Keep cursor inside board after it is transformed
+    pub fn clamp(&mut self, mapping: &Mapping) {
+        self.cell.column = self.cell.column.clamp(1, mapping.columns - 2);
+        self.cell.row = self.cell.row.clamp(1, mapping.rows - 2);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Calendar day, UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// Day of `seconds` since Unix epoch
+    pub fn from_unix_time(seconds: f64) -> Self {
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let days = (seconds / 86400.).floor() as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+        let month = if shifted_month < 10 {
+            shifted_month + 3
+        } else {
+            shifted_month - 9
+        } as u8;
+        let year = (year_of_era + era * 400 + (month <= 2) as i64) as i32;
+        Self { year, month, day }
+    }
+}
+
+/// `YYYY-MM-DD`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.trim().splitn(3, '-');
+        let mut field = || fields.next().ok_or("date needs year, month and day");
+        let date = Self {
+            year: field()?.parse().map_err(|_| "invalid year")?,
+            month: field()?.parse().map_err(|_| "invalid month")?,
+            day: field()?.parse().map_err(|_| "invalid day")?,
+        };
+        if !(1..=12).contains(&date.month) || !(1..=31).contains(&date.day) {
+            return Err("invalid date");
+        }
+        Ok(date)
+    }
+}
+
+/// Board of the day, the same for everyone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Daily {
+    pub date: Date,
+}
+
+impl Daily {
+    pub fn new(date: Date) -> Self {
+        Self { date }
+    }
+
+    pub fn seed(&self) -> u64 {
+        let date = self.date;
+        let days = (date.year as u64) << 16 | (date.month as u64) << 8 | date.day as u64;
+        Rng::new(days).next_u64()
+    }
+
+    /// Variant of the day, drawn from date so it doesn't depend on build
+    pub fn rules(&self) -> Rules {
+        let mut rng = Rng::new(self.seed().rotate_left(17));
+        let mut rules = Rules {
+            tiles: [48, 64, 80][rng.gen_range(0, 3)],
+            shuffles: Some(3),
+            ..Default::default()
+        };
+        if rng.gen_range(0, 3) == 0 {
+            let directions = [
+                Direction::Up,
+                Direction::Down,
+                Direction::Left,
+                Direction::Right,
+            ];
+            rules.gravity = Some(directions[rng.gen_range(0, directions.len())]);
+        }
+        if rng.gen_range(0, 3) == 0 {
+            rules.specials = vec![Special::Wildcard, Special::Bomb, Special::Bomb];
+        }
+        if rng.gen_range(0, 4) == 0 {
+            rules.matching = Matching::Families { size: 2 };
+        }
+        rules
+    }
+
+    pub fn session(&self) -> GameSession {
+        GameSession::new(self.rules(), self.seed())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Started but not finished yet
+    Playing,
+    Cleared,
+    Failed,
+}
+
+impl Outcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Playing => "playing",
+            Self::Cleared => "cleared",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// How a daily challenge went
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyResult {
+    pub date: Date,
+    pub outcome: Outcome,
+    /// Seconds of game clock
+    pub time: f64,
+    pub misses: usize,
+    pub hints: usize,
+    pub score: i64,
+}
+
+impl DailyResult {
+    /// Result of `session` so far
+    pub fn new(date: Date, session: &GameSession) -> Self {
+        let stats = session.stats();
+        Self {
+            date,
+            outcome: match session.state() {
+                State::Won => Outcome::Cleared,
+                State::Lost | State::Stuck => Outcome::Failed,
+                _ => Outcome::Playing,
+            },
+            time: session.clock(),
+            misses: stats.failed,
+            hints: stats.hints,
+            score: session.scoring().total(),
+        }
+    }
+
+    /// One line to paste elsewhere, e.g.
+    /// `Meme Connect 2026-10-19: cleared in 3:05, 2 misses, 1 hints`
+    pub fn share(&self) -> String {
+        let time = self.time.max(0.) as u64;
+        let outcome = match self.outcome {
+            Outcome::Cleared => format!("cleared in {}:{:02}", time / 60, time % 60),
+            Outcome::Failed => "failed".to_string(),
+            Outcome::Playing => "unfinished".to_string(),
+        };
+        format!(
+            "Meme Connect {}: {}, {} misses, {} hints",
+            self.date, outcome, self.misses, self.hints
+        )
+    }
+}
+
+/// `<date>=<outcome>,<time>,<misses>,<hints>,<score>`
+impl fmt::Display for DailyResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}={},{},{},{},{}",
+            self.date,
+            self.outcome.name(),
+            self.time,
+            self.misses,
+            self.hints,
+            self.score
+        )
+    }
+}
+
+impl FromStr for DailyResult {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, value) = s.split_once('=').ok_or("missing `=` in line")?;
+        let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+        let [outcome, time, misses, hints, score] = fields[..] else {
+            return Err("daily result needs 5 fields");
+        };
+        Ok(Self {
+            date: date.parse()?,
+            outcome: [Outcome::Playing, Outcome::Cleared, Outcome::Failed]
+                .into_iter()
+                .find(|candidate| candidate.name() == outcome)
+                .ok_or("invalid outcome")?,
+            time: time.parse().map_err(|_| "invalid time")?,
+            misses: misses.parse().map_err(|_| "invalid misses")?,
+            hints: hints.parse().map_err(|_| "invalid hints")?,
+            score: score.parse().map_err(|_| "invalid score")?,
+        })
+    }
+}
+
+/// Ranked attempt of each day played, kept by player. An attempt counts as
+/// soon as it starts, quitting it counts as failed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailyLog {
+    /// Oldest first
+    pub results: Vec<DailyResult>,
+}
+
+impl DailyLog {
+    /// Ranked result of `date`, if already attempted
+    pub fn ranked(&self, date: Date) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.date == date)
+    }
+
+    /// Start attempt of `date`, true if it is the ranked one. Ranked attempt
+    /// still playing was abandoned and fails.
+    pub fn start(&mut self, date: Date) -> bool {
+        if let Some(ranked) = self.results.iter_mut().find(|result| result.date == date) {
+            if ranked.outcome == Outcome::Playing {
+                ranked.outcome = Outcome::Failed;
+            }
+            return false;
+        }
+        self.results.push(DailyResult {
+            date,
+            outcome: Outcome::Playing,
+            time: 0.,
+            misses: 0,
+            hints: 0,
+            score: 0,
+        });
+        true
+    }
+
+    /// Keep `result` of the ranked attempt, later attempts are practice
+    pub fn finish(&mut self, result: DailyResult) -> bool {
+        match self
+            .results
+            .iter_mut()
+            .find(|ranked| ranked.date == result.date && ranked.outcome == Outcome::Playing)
+        {
+            Some(ranked) => {
+                *ranked = result;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// One result per line
+impl fmt::Display for DailyLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in self.results.iter() {
+            writeln!(f, "{}", result)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DailyLog {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            results: s
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
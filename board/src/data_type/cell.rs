@@ -1,7 +1,7 @@
 use super::direction::Direction;
 use std::{collections::HashMap, iter};
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Cell {
     pub column: usize,
     pub row: usize,
@@ -14,11 +14,13 @@ pub enum Axis {
 
 impl Direction {
     pub fn is_opposite(&self, other: &Self) -> bool {
-        match (self, other) {
-            (&Self::Up, &Self::Down) | (&Self::Down, &Self::Up) => true,
-            (&Self::Left, &Self::Right) | (&Self::Right, &Self::Left) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (&Self::Up, &Self::Down)
+                | (&Self::Down, &Self::Up)
+                | (&Self::Left, &Self::Right)
+                | (&Self::Right, &Self::Left)
+        )
     }
 
     pub fn axis(&self) -> Axis {
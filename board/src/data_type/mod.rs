@@ -1,4 +1,5 @@
 pub mod cell;
 pub mod direction;
 pub mod prelude;
+pub mod special;
 pub mod sprite;
@@ -1 +1 @@
-pub use super::{cell::*, direction::*, sprite::*};
+pub use super::{cell::*, direction::*, special::*, sprite::*};
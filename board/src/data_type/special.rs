@@ -0,0 +1,10 @@
+/// Tile with effect, placed on top of a regular sprite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Special {
+    /// Match any sprite
+    Wildcard,
+    /// Clear four neighbours when matched
+    Bomb,
+    /// Change sprite every `period` moves
+    Chameleon { period: usize },
+}
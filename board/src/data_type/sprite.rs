@@ -1,2 +1,48 @@
+use std::collections::HashMap;
+
 pub type SpriteId = usize;
 pub const NO_SPRITE: SpriteId = 0;
+
+/// Family of sprites, e.g. memes of the same template
+pub type CategoryId = usize;
+
+/// Metadata of sprites, mapping each sprite to its categories
+#[derive(Debug, Default, Clone)]
+pub struct SpriteTable {
+    categories: HashMap<SpriteId, Vec<CategoryId>>,
+}
+
+impl SpriteTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Group consecutive sprites of `sprites` into categories of `size`
+    /// sprites each, numbered from 0
+    pub fn chunked(sprites: impl Iterator<Item = SpriteId>, size: usize) -> Self {
+        let mut table = Self::new();
+        for (idx, sprite) in sprites.enumerate() {
+            table.insert(sprite, idx / size.max(1));
+        }
+        table
+    }
+
+    /// Add `sprite` to `category`
+    pub fn insert(&mut self, sprite: SpriteId, category: CategoryId) {
+        let categories = self.categories.entry(sprite).or_default();
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+
+    pub fn categories(&self, sprite: SpriteId) -> &[CategoryId] {
+        self.categories.get(&sprite).map_or(&[], |c| c.as_slice())
+    }
+
+    pub fn share_category(&self, first: SpriteId, second: SpriteId) -> bool {
+        let second_categories = self.categories(second);
+        self.categories(first)
+            .iter()
+            .any(|category| second_categories.contains(category))
+    }
+}
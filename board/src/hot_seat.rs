@@ -0,0 +1,147 @@
+use crate::prelude::*;
+use std::{cell::RefCell, rc::Rc};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    pub name: String,
+    pub score: i64,
+    pub matches: usize,
+}
+
+impl Player {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            score: 0,
+            matches: 0,
+        }
+    }
+}
+
+/// Matches of each player and whether current turn missed, collected from
+/// board events
+#[derive(Default)]
+struct Tally {
+    matches: Vec<usize>,
+    missed: bool,
+}
+
+impl ConnectorListener for Tally {
+    fn on_match(&mut self, couple: &Couple) {
+        if let Some(matches) = self.matches.get_mut(couple.player) {
+            *matches += 1;
+        }
+    }
+
+    fn on_fail(&mut self, _cells: &[Cell], _failure: Failure) {
+        self.missed = true;
+    }
+}
+
+/// Players taking turns on one board. Turn passes on a miss or when turn
+/// time is up.
+pub struct HotSeat {
+    session: GameSession,
+    players: Vec<Player>,
+    current: PlayerId,
+    /// Seconds each turn lasts
+    pub turn_time: f64,
+    turn_left: f64,
+    tally: Rc<RefCell<Tally>>,
+}
+
+impl HotSeat {
+    pub const MIN_PLAYERS: usize = 2;
+    pub const MAX_PLAYERS: usize = 4;
+
+    pub fn new(
+        rules: Rules,
+        seed: u64,
+        names: &[&str],
+        turn_time: f64,
+    ) -> Result<Self, &'static str> {
+        if !(Self::MIN_PLAYERS..=Self::MAX_PLAYERS).contains(&names.len()) {
+            return Err("hot-seat needs 2 to 4 players");
+        }
+        if turn_time <= 0. {
+            return Err("turn time must be positive");
+        }
+        let mut session = GameSession::new(rules, seed);
+        let tally = Rc::new(RefCell::new(Tally {
+            matches: vec![0; names.len()],
+            missed: false,
+        }));
+        session.subscribe(tally.clone());
+        Ok(Self {
+            session,
+            players: names.iter().map(|name| Player::new(name)).collect(),
+            current: 0,
+            turn_time,
+            turn_left: turn_time,
+            tally,
+        })
+    }
+
+    pub fn session(&self) -> &GameSession {
+        &self.session
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    /// Player whose turn it is
+    pub fn current(&self) -> PlayerId {
+        self.current
+    }
+
+    pub fn turn_left(&self) -> f64 {
+        self.turn_left
+    }
+
+    /// Players with the highest score, several on a draw
+    pub fn leaders(&self) -> Vec<PlayerId> {
+        let best = self.players.iter().map(|player| player.score).max();
+        (0..self.players.len())
+            .filter(|idx| Some(self.players[*idx].score) == best)
+            .collect()
+    }
+
+    fn pass_turn(&mut self) -> Event {
+        self.current = (self.current + 1) % self.players.len();
+        self.turn_left = self.turn_time;
+        self.session.set_player(self.current);
+        Event::TurnPassed(self.current)
+    }
+
+    /// Apply input of current player, undo is not allowed as it would revert
+    /// other players' matches
+    pub fn update(&mut self, input: &[Action], dt: f64) -> Vec<Event> {
+        let input: Vec<Action> = input
+            .iter()
+            .filter(|action| **action != Action::Undo)
+            .copied()
+            .collect();
+        let mut events = self.session.update(&input, dt);
+        for event in events.iter() {
+            if let Event::Score(change) = event {
+                self.players[self.current].score += change.delta;
+            }
+        }
+        let missed = {
+            let mut tally = self.tally.borrow_mut();
+            for (player, matches) in self.players.iter_mut().zip(tally.matches.iter()) {
+                player.matches = *matches;
+            }
+            std::mem::take(&mut tally.missed)
+        };
+
+        if self.session.state() == State::Playing {
+            self.turn_left = (self.turn_left - dt).max(0.);
+        }
+        if !self.session.state().is_over() && (missed || self.turn_left == 0.) {
+            events.push(self.pass_turn());
+        }
+        events
+    }
+}
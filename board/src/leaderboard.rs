@@ -0,0 +1,309 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Which table a game belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableKey {
+    /// Game mode, e.g. `free` or `daily`
+    pub mode: String,
+    pub columns: u8,
+    pub rows: u8,
+    /// Rule variants making the game easier or harder, see `difficulty`
+    pub difficulty: String,
+}
+
+impl TableKey {
+    pub fn new(mode: &str, rules: &Rules) -> Self {
+        Self {
+            mode: mode.to_string(),
+            columns: rules.columns,
+            rows: rules.rows,
+            difficulty: difficulty(rules),
+        }
+    }
+}
+
+/// Rule variants joined by `+`, `classic` when there is none
+pub fn difficulty(rules: &Rules) -> String {
+    let variants = [
+        (rules.group_size == 3, "triple"),
+        (rules.matching != Matching::Identity, "families"),
+        (!rules.specials.is_empty(), "specials"),
+        (rules.gravity.is_some(), "gravity"),
+        (rules.face_down.is_some(), "face-down"),
+        (rules.time_limit.is_some(), "timed"),
+        (rules.shuffles.is_some(), "limited-shuffles"),
+        (
+            !rules.power_ups.is_empty() || rules.earn_every.is_some(),
+            "power-ups",
+        ),
+    ];
+    let names: Vec<&str> = variants
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+    match names.is_empty() {
+        true => "classic".to_string(),
+        false => names.join("+"),
+    }
+}
+
+/// `<mode> <columns> <rows> <difficulty>`
+impl fmt::Display for TableKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.mode, self.columns, self.rows, self.difficulty
+        )
+    }
+}
+
+impl FromStr for TableKey {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [mode, columns, rows, difficulty] = fields[..] else {
+            return Err("table needs mode, size and difficulty");
+        };
+        Ok(Self {
+            mode: mode.to_string(),
+            columns: columns.parse().map_err(|_| "invalid columns")?,
+            rows: rows.parse().map_err(|_| "invalid rows")?,
+            difficulty: difficulty.to_string(),
+        })
+    }
+}
+
+/// Replay keys are `record_` and 16 hex digits, anything else read from a
+/// file could reach outside of saves
+fn is_replay_key(key: &str) -> bool {
+    key.strip_prefix("record_")
+        .is_some_and(|id| id.len() == 16 && id.chars().all(|digit| digit.is_ascii_hexdigit()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub score: i64,
+    /// Seconds of game clock
+    pub time: f64,
+    pub date: Date,
+    pub seed: u64,
+    /// Storage key of replay of this game
+    pub replay: String,
+}
+
+impl Entry {
+    /// Entry of finished `session`, played by `name` on `date`
+    pub fn new(name: &str, date: Date, session: &GameSession) -> Self {
+        let score = session.scoring().total();
+        let time = session.clock();
+        let seed = session.replay().seed;
+        let day = (date.year as u64) << 16 | (date.month as u64) << 8 | date.day as u64;
+        let id =
+            Rng::new(seed ^ score as u64 ^ time.to_bits() ^ Rng::new(day).next_u64()).next_u64();
+        Self {
+            name: name.to_string(),
+            score,
+            time,
+            date,
+            seed,
+            replay: format!("record_{:016x}", id),
+        }
+    }
+
+    /// Higher score first, faster first on a tie
+    fn beats(&self, other: &Self) -> bool {
+        self.score > other.score || (self.score == other.score && self.time < other.time)
+    }
+}
+
+/// `<score>,<time>,<date>,<seed>,<replay>,<name>`, name last as it may
+/// contain commas
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{},{},{},{},{},{}",
+            self.score, self.time, self.date, self.seed, self.replay, self.name
+        )
+    }
+}
+
+impl FromStr for Entry {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.splitn(6, ',').collect();
+        let [score, time, date, seed, replay, name] = fields[..] else {
+            return Err("entry needs 6 fields");
+        };
+        let replay = replay.trim();
+        if !is_replay_key(replay) {
+            return Err("invalid replay key");
+        }
+        Ok(Self {
+            name: name.trim().to_string(),
+            score: score.trim().parse().map_err(|_| "invalid score")?,
+            time: time.trim().parse().map_err(|_| "invalid time")?,
+            date: date.parse()?,
+            seed: seed.trim().parse().map_err(|_| "invalid seed")?,
+            replay: replay.to_string(),
+        })
+    }
+}
+
+/// Replay text by storage key, as carried by exported tables
+pub type Replays = Vec<(String, String)>;
+
+/// Best entries of one table, best first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub key: TableKey,
+    pub entries: Vec<Entry>,
+}
+
+/// Every table of a player
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Leaderboards {
+    pub tables: Vec<Table>,
+}
+
+impl Leaderboards {
+    /// Entries kept in each table
+    pub const TABLE_SIZE: usize = 10;
+
+    pub fn table(&self, key: &TableKey) -> Option<&Table> {
+        self.tables.iter().find(|table| table.key == *key)
+    }
+
+    /// Give `entry` a replay key no entry uses yet, e.g. for a daily played
+    /// again with the same result
+    pub fn unique_replay(&self, entry: &mut Entry) {
+        let taken = |replay: &str| {
+            self.tables
+                .iter()
+                .flat_map(|table| table.entries.iter())
+                .any(|other| other.replay == replay)
+        };
+        while taken(&entry.replay) {
+            let id = u64::from_str_radix(&entry.replay["record_".len()..], 16).unwrap_or_default();
+            entry.replay = format!("record_{:016x}", Rng::new(id).next_u64());
+        }
+    }
+
+    /// Add `entry` to table of `key`. Return its rank from 0, or `None` if it
+    /// didn't make the table, with the entry pushed off the table if any.
+    pub fn insert(&mut self, key: &TableKey, entry: Entry) -> (Option<usize>, Option<Entry>) {
+        let idx = match self.tables.iter().position(|table| table.key == *key) {
+            Some(idx) => idx,
+            None => {
+                self.tables.push(Table {
+                    key: key.clone(),
+                    entries: Vec::new(),
+                });
+                self.tables.len() - 1
+            }
+        };
+        let entries = &mut self.tables[idx].entries;
+        if entries.contains(&entry) {
+            return (None, None);
+        }
+        let rank = entries
+            .iter()
+            .position(|other| entry.beats(other))
+            .unwrap_or(entries.len());
+        if rank >= Self::TABLE_SIZE {
+            return (None, Some(entry));
+        }
+        entries.insert(rank, entry);
+        let evicted = (entries.len() > Self::TABLE_SIZE).then(|| entries.pop().unwrap());
+        (Some(rank), evicted)
+    }
+
+    /// Add every entry of `other`, return entries pushed off tables
+    pub fn merge(&mut self, other: Leaderboards) -> Vec<Entry> {
+        let mut evicted = Vec::new();
+        for table in other.tables {
+            for entry in table.entries {
+                evicted.extend(self.insert(&table.key, entry).1);
+            }
+        }
+        evicted
+    }
+
+    /// Tables followed by replay of each entry found by `replay`, replay lines
+    /// being prefixed with replay key and `.`
+    pub fn export(&self, replay: impl Fn(&str) -> Option<String>) -> String {
+        let mut text = self.to_string();
+        for entry in self.tables.iter().flat_map(|table| table.entries.iter()) {
+            for line in replay(&entry.replay).unwrap_or_default().lines() {
+                text.push_str(&format!("{}.{}\n", entry.replay, line));
+            }
+        }
+        text
+    }
+
+    /// Read text written by `Leaderboards::export`, return tables and replay
+    /// of each entry by key
+    pub fn import(text: &str) -> Result<(Self, Replays), &'static str> {
+        let mut tables = String::new();
+        let mut replays = Replays::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            match line.split_once('.') {
+                Some((key, line)) if is_replay_key(key) => {
+                    match replays.iter_mut().find(|(replay, _)| replay == key) {
+                        Some((_, replay)) => replay.push_str(&format!("{}\n", line)),
+                        None => replays.push((key.to_string(), format!("{}\n", line))),
+                    }
+                }
+                _ => {
+                    tables.push_str(line);
+                    tables.push('\n');
+                }
+            }
+        }
+        Ok((tables.parse()?, replays))
+    }
+}
+
+/// `table=<key>` line followed by `entry=<entry>` lines of that table
+impl fmt::Display for Leaderboards {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for table in self.tables.iter() {
+            writeln!(f, "table={}", table.key)?;
+            for entry in table.entries.iter() {
+                writeln!(f, "entry={}", entry)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Leaderboards {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut leaderboards = Self::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            match key.trim() {
+                "table" => leaderboards.tables.push(Table {
+                    key: value.parse()?,
+                    entries: Vec::new(),
+                }),
+                "entry" => leaderboards
+                    .tables
+                    .last_mut()
+                    .ok_or("entry before any table")?
+                    .entries
+                    .push(value.parse()?),
+                _ => return Err("unknown key"),
+            }
+        }
+        Ok(leaderboards)
+    }
+}
@@ -1,5 +1,24 @@
+mod achievement;
+mod bot;
+mod campaign;
+mod cursor;
+mod daily;
 pub mod data_type;
+mod hot_seat;
+mod leaderboard;
 mod mapping;
+mod net;
+mod power_up;
 pub mod prelude;
+mod puzzle;
+mod random;
+mod replay;
+mod rule;
+mod score;
+pub mod session;
+mod stats;
 mod tracing;
+mod transform;
 pub mod update;
+mod versus;
+mod visibility;
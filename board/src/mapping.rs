@@ -1,10 +1,39 @@
 use crate::prelude::*;
-use std::iter::repeat;
+use std::{collections::HashMap, iter::repeat};
 
+#[derive(Clone)]
 pub struct Mapping {
     pub columns: usize,
     pub rows: usize,
     data: Vec<SpriteId>,
+    /// Special effects on top of sprites
+    specials: HashMap<Cell, Special>,
+    /// Zobrist hash of sprites and specials, kept up to date on every change
+    hash: u64,
+}
+
+/// Zobrist key of `value` on `cell`, derived from position instead of a
+/// table so boards of any size share the same keys
+fn zobrist_key(cell: &Cell, value: u64) -> u64 {
+    Rng::new(((cell.column as u64) << 48) ^ ((cell.row as u64) << 32) ^ value).next_u64()
+}
+
+fn sprite_key(cell: &Cell, sprite: SpriteId) -> u64 {
+    match sprite {
+        NO_SPRITE => 0,
+        sprite => zobrist_key(cell, sprite as u64),
+    }
+}
+
+fn special_key(cell: &Cell, special: Option<Special>) -> u64 {
+    // High bit keeps specials apart from sprites
+    let code = match special {
+        None => return 0,
+        Some(Special::Wildcard) => 1,
+        Some(Special::Bomb) => 2,
+        Some(Special::Chameleon { period }) => 3 | (period as u64) << 8,
+    };
+    zobrist_key(cell, code | 1 << 31)
 }
 
 impl Default for Mapping {
@@ -13,6 +42,8 @@ impl Default for Mapping {
             columns: 12,
             rows: 12,
             data: vec![NO_SPRITE; 12 * 12],
+            specials: HashMap::new(),
+            hash: 0,
         }
     }
 }
@@ -26,6 +57,8 @@ impl Mapping {
             columns: actual_columns,
             rows: actual_rows,
             data: vec![NO_SPRITE; actual_columns * actual_rows],
+            specials: HashMap::new(),
+            hash: 0,
         }
     }
 
@@ -41,6 +74,20 @@ impl Mapping {
         self.data[cell.row * self.columns + cell.column]
     }
 
+    pub fn get_special(&self, cell: &Cell) -> Option<Special> {
+        self.specials.get(cell).copied()
+    }
+
+    pub fn specials(&self) -> impl Iterator<Item = (&Cell, &Special)> {
+        self.specials.iter()
+    }
+
+    /// Hash of sprites and specials, equal boards have equal hashes. Updated
+    /// incrementally, so it is cheap to check every update.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
     fn get_row(&self, index: usize) -> Vec<SpriteId> {
         self.data
             .iter()
@@ -62,6 +109,12 @@ impl Mapping {
             .map(|(column, row)| Cell { column, row })
     }
 
+    /// No tile left on board
+    pub fn is_cleared(&self) -> bool {
+        self.mutable_cells()
+            .all(|cell| self.get_sprite(&cell) == NO_SPRITE)
+    }
+
     pub fn _fmt(&self) -> String {
         self.get_rows()
             .iter()
@@ -107,15 +160,67 @@ impl Mapping {
     ///////////////////////////// Manipulate data /////////////////////////////
     ///////////////////////////////////////////////////////////////////////////
 
-    pub fn clear_cell(&mut self, cell: &Cell) {
+    /// Only place sprites are written, to keep hash up to date
+    fn put_sprite(&mut self, cell: &Cell, sprite: SpriteId) {
         let idx = self.cell2index(cell);
-        self.data[idx] = NO_SPRITE;
+        self.hash ^= sprite_key(cell, self.data[idx]) ^ sprite_key(cell, sprite);
+        self.data[idx] = sprite;
+    }
+
+    /// Only place specials are written, to keep hash up to date
+    fn put_special(&mut self, cell: &Cell, special: Option<Special>) {
+        let old = match special {
+            Some(special) => self.specials.insert(*cell, special),
+            None => self.specials.remove(cell),
+        };
+        self.hash ^= special_key(cell, old) ^ special_key(cell, special);
+    }
+
+    /// Hash computed from scratch
+    fn full_hash(&self) -> u64 {
+        let sprites = (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| Cell { column, row }))
+            .map(|cell| sprite_key(&cell, self.get_sprite(&cell)));
+        let specials = self
+            .specials
+            .iter()
+            .map(|(cell, special)| special_key(cell, Some(*special)));
+        sprites.chain(specials).fold(0, |hash, key| hash ^ key)
+    }
+
+    pub fn clear_cell(&mut self, cell: &Cell) {
+        self.put_sprite(cell, NO_SPRITE);
+        self.put_special(cell, None);
+    }
+
+    /// Put special effect on an occupied cell
+    pub fn set_special(&mut self, cell: &Cell, special: Special) -> Result<(), &'static str> {
+        if self.get_sprite(cell) == NO_SPRITE {
+            return Err("cell is empty");
+        }
+        self.put_special(cell, Some(special));
+        Ok(())
+    }
+
+    /// Change sprite of an occupied cell, its special stays
+    pub fn set_sprite(&mut self, cell: &Cell, sprite: SpriteId) -> Result<(), &'static str> {
+        if self.get_sprite(cell) == NO_SPRITE {
+            return Err("cell is empty");
+        }
+        self.put_sprite(cell, sprite);
+        Ok(())
+    }
+
+    /// Exchange sprites of 2 cells, specials stay in place
+    pub fn swap_sprites(&mut self, first: &Cell, second: &Cell) {
+        let first_sprite = self.get_sprite(first);
+        self.put_sprite(first, self.get_sprite(second));
+        self.put_sprite(second, first_sprite);
     }
 
     pub fn fill_cell(&mut self, cell: &Cell, sprite: SpriteId) -> Result<(), &'static str> {
         self.check_fillable_cell(cell)?;
-        let idx = self.cell2index(cell);
-        self.data[idx] = sprite;
+        self.put_sprite(cell, sprite);
         Ok(())
     }
 
@@ -125,16 +230,113 @@ impl Mapping {
         &mut self,
         regions: &mut Vec<Cell>,
         sprite_list: impl Iterator<Item = SpriteId>,
+    ) -> Result<(), &'static str> {
+        self.fill_groups(regions, sprite_list, 2)
+    }
+
+    /// Same as `fill_regions`, but each sprite is set `group_size` times.
+    /// Note: `regions` is consumed
+    pub fn fill_groups(
+        &mut self,
+        regions: &mut Vec<Cell>,
+        sprite_list: impl Iterator<Item = SpriteId>,
+        group_size: usize,
     ) -> Result<(), &'static str> {
         for sprite in sprite_list {
-            if regions.len() < 2 {
+            if regions.len() < group_size {
                 break;
             }
-            for _ in 0..2 {
+            for _ in 0..group_size {
                 let cell = regions.pop().unwrap();
                 self.fill_cell(&cell, sprite)?;
             }
         }
         Ok(())
     }
+
+    /// Slide remaining tiles toward `direction` until they hit the border or
+    /// another tile, specials move along with their tiles
+    pub fn collapse(&mut self, direction: Direction) {
+        // Lines parallel to direction, ordered from the destination side
+        let lines: Vec<Vec<Cell>> = match direction {
+            Direction::Up | Direction::Down => (1..self.columns - 1)
+                .map(|column| (1..self.rows - 1).map(|row| Cell { column, row }).collect())
+                .collect(),
+            Direction::Left | Direction::Right => (1..self.rows - 1)
+                .map(|row| {
+                    (1..self.columns - 1)
+                        .map(|column| Cell { column, row })
+                        .collect()
+                })
+                .collect(),
+        };
+        for mut line in lines {
+            if matches!(direction, Direction::Down | Direction::Right) {
+                line.reverse();
+            }
+            let tiles: Vec<(SpriteId, Option<Special>)> = line
+                .iter()
+                .filter(|cell| self.get_sprite(cell) != NO_SPRITE)
+                .map(|cell| (self.get_sprite(cell), self.get_special(cell)))
+                .collect();
+            line.iter().for_each(|cell| self.clear_cell(cell));
+            for (cell, (sprite, special)) in line.iter().zip(tiles) {
+                self.put_sprite(cell, sprite);
+                self.put_special(cell, special);
+            }
+        }
+    }
+
+    /// Redistribute remaining tiles over their cells at random, specials move
+    /// along with their tiles
+    pub fn shuffle(&mut self, rng: &mut Rng) {
+        let occupied: Vec<Cell> = self
+            .mutable_cells()
+            .filter(|cell| self.get_sprite(cell) != NO_SPRITE)
+            .collect();
+        let mut tiles: Vec<(SpriteId, Option<Special>)> = occupied
+            .iter()
+            .map(|cell| (self.get_sprite(cell), self.get_special(cell)))
+            .collect();
+        // Fisher-Yates
+        for idx in (1..tiles.len()).rev() {
+            tiles.swap(idx, rng.gen_range(0, idx + 1));
+        }
+        for (cell, (sprite, special)) in occupied.iter().zip(tiles) {
+            self.put_sprite(cell, sprite);
+            self.put_special(cell, special);
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    /////////////////////////////// Transform /////////////////////////////////
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Position of `cell` after applying `transform` on current board
+    pub fn transform_cell(&self, transform: Transform, cell: &Cell) -> Cell {
+        transform.apply(cell, self.columns, self.rows)
+    }
+
+    /// Move every sprite (border included) to its transformed position
+    pub fn transform(&mut self, transform: Transform) {
+        let (columns, rows) = transform.dimensions(self.columns, self.rows);
+        let mut data = vec![NO_SPRITE; columns * rows];
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let cell = Cell { column, row };
+                let target = self.transform_cell(transform, &cell);
+                data[target.row * columns + target.column] = self.get_sprite(&cell);
+            }
+        }
+        let (old_columns, old_rows) = (self.columns, self.rows);
+        self.specials = self
+            .specials
+            .drain()
+            .map(|(cell, special)| (transform.apply(&cell, old_columns, old_rows), special))
+            .collect();
+        self.columns = columns;
+        self.rows = rows;
+        self.data = data;
+        self.hash = self.full_hash();
+    }
 }
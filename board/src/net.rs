@@ -0,0 +1,197 @@
+//! Messages between versus server and clients, one text message per
+//! WebSocket frame. The server runs the race and broadcasts every tick with
+//! input of each player, so clients run the same race in lockstep.
+
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Port server listens to by default
+pub const DEFAULT_PORT: u16 = 9001;
+
+/// Seconds between server ticks
+pub const TICK: f64 = 1. / 30.;
+
+/// Ticks between checksums of every board
+pub const CHECK_TICKS: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    Join {
+        name: String,
+    },
+    /// Actions on own board since previous message
+    Input(Vec<Action>),
+    /// Board of player diverged, ask for a snapshot
+    Resync(PlayerId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerMessage {
+    /// Race starts, everything needed to build the same `Versus`
+    Welcome {
+        player: PlayerId,
+        players: usize,
+        seed: u64,
+        time_limit: f64,
+        rules: Box<Rules>,
+    },
+    /// One tick of race, input in player order
+    Frame { dt: f64, inputs: Vec<Vec<Action>> },
+    /// Checksum of each board after `tick` frames, in player order
+    Check { tick: u64, checksums: Vec<u64> },
+    /// Authoritative board of `player` after `tick` frames, as written by
+    /// `GameSession::save`
+    Snapshot {
+        tick: u64,
+        player: PlayerId,
+        time_left: f64,
+        save: String,
+    },
+    /// Player disconnected, race is over
+    Left(PlayerId),
+}
+
+fn write_actions(actions: &[Action]) -> String {
+    actions
+        .iter()
+        .map(Action::to_string)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn parse_actions(s: &str) -> Result<Vec<Action>, &'static str> {
+    s.split(';')
+        .filter(|action| !action.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+impl fmt::Display for ClientMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Join { name } => write!(f, "join {}", name),
+            Self::Input(actions) => write!(f, "input {}", write_actions(actions)),
+            Self::Resync(player) => write!(f, "resync {}", player),
+        }
+    }
+}
+
+impl FromStr for ClientMessage {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, body) = s.split_once(' ').unwrap_or((s, ""));
+        match kind {
+            "join" => Ok(Self::Join {
+                name: body.trim().to_string(),
+            }),
+            "input" => parse_actions(body).map(Self::Input),
+            "resync" => Ok(Self::Resync(
+                body.trim().parse().map_err(|_| "invalid player")?,
+            )),
+            _ => Err("unknown client message"),
+        }
+    }
+}
+
+/// `welcome` header followed by rules lines, `frame <dt>|<actions>|...`,
+/// `check <tick> <checksum>...`, `snapshot` header followed by save lines or
+/// `left <player>`
+impl fmt::Display for ServerMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Welcome {
+                player,
+                players,
+                seed,
+                time_limit,
+                rules,
+            } => write!(
+                f,
+                "welcome {} {} {} {}\n{}",
+                player, players, seed, time_limit, rules
+            ),
+            Self::Frame { dt, inputs } => {
+                write!(f, "frame {}", dt)?;
+                for actions in inputs.iter() {
+                    write!(f, "|{}", write_actions(actions))?;
+                }
+                Ok(())
+            }
+            Self::Check { tick, checksums } => {
+                write!(f, "check {}", tick)?;
+                for checksum in checksums.iter() {
+                    write!(f, " {}", checksum)?;
+                }
+                Ok(())
+            }
+            Self::Snapshot {
+                tick,
+                player,
+                time_left,
+                save,
+            } => write!(f, "snapshot {} {} {}\n{}", tick, player, time_left, save),
+            Self::Left(player) => write!(f, "left {}", player),
+        }
+    }
+}
+
+impl FromStr for ServerMessage {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, rest) = s.split_once('\n').unwrap_or((s, ""));
+        let (kind, body) = header.split_once(' ').unwrap_or((header, ""));
+        match kind {
+            "welcome" => {
+                let fields: Vec<&str> = body.split_whitespace().collect();
+                let [player, players, seed, time_limit] = fields[..] else {
+                    return Err("welcome needs 4 fields");
+                };
+                Ok(Self::Welcome {
+                    player: player.parse().map_err(|_| "invalid player")?,
+                    players: players.parse().map_err(|_| "invalid players")?,
+                    seed: seed.parse().map_err(|_| "invalid seed")?,
+                    time_limit: time_limit.parse().map_err(|_| "invalid time limit")?,
+                    rules: Box::new(rest.parse()?),
+                })
+            }
+            "frame" => {
+                let mut parts = body.split('|');
+                let dt = parts
+                    .next()
+                    .and_then(|dt| dt.trim().parse().ok())
+                    .ok_or("invalid frame time")?;
+                let inputs = parts.map(parse_actions).collect::<Result<_, _>>()?;
+                Ok(Self::Frame { dt, inputs })
+            }
+            "check" => {
+                let mut fields = body.split_whitespace().map(str::parse::<u64>);
+                let tick = fields
+                    .next()
+                    .and_then(Result::ok)
+                    .ok_or("invalid check tick")?;
+                let checksums = fields
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| "invalid checksum")?;
+                Ok(Self::Check { tick, checksums })
+            }
+            "snapshot" => {
+                let fields: Vec<&str> = body.split_whitespace().collect();
+                let [tick, player, time_left] = fields[..] else {
+                    return Err("snapshot needs 3 fields");
+                };
+                Ok(Self::Snapshot {
+                    tick: tick.parse().map_err(|_| "invalid tick")?,
+                    player: player.parse().map_err(|_| "invalid player")?,
+                    time_left: time_left.parse().map_err(|_| "invalid time left")?,
+                    save: rest.to_string(),
+                })
+            }
+            "left" => Ok(Self::Left(
+                body.trim().parse().map_err(|_| "invalid player")?,
+            )),
+            _ => Err("unknown server message"),
+        }
+    }
+}
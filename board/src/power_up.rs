@@ -0,0 +1,96 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+/// Consumable item triggered by player
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PowerUp {
+    /// Shuffle for free, regardless of shuffle allowance
+    Shuffle,
+    /// Show a group that can be matched, without penalty
+    Hint,
+    /// Next matching selection is cleared regardless of path
+    Bomb,
+    /// Stop countdown for a while
+    Freeze,
+    /// Rotate board clockwise
+    Rotate,
+}
+
+impl PowerUp {
+    pub const ALL: [Self; 5] = [
+        Self::Shuffle,
+        Self::Hint,
+        Self::Bomb,
+        Self::Freeze,
+        Self::Rotate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Shuffle => "Shuffle",
+            Self::Hint => "Hint",
+            Self::Bomb => "Bomb",
+            Self::Freeze => "Freeze",
+            Self::Rotate => "Rotate",
+        }
+    }
+}
+
+impl FromStr for PowerUp {
+    type Err = &'static str;
+
+    /// Parse name given by `PowerUp::name`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|power_up| power_up.name() == s)
+            .ok_or("unknown power-up")
+    }
+}
+
+/// Power-ups owned by player
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Inventory {
+    items: BTreeMap<PowerUp, usize>,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, power_up: PowerUp, count: usize) {
+        *self.items.entry(power_up).or_default() += count;
+    }
+
+    pub fn count(&self, power_up: PowerUp) -> usize {
+        self.items.get(&power_up).copied().unwrap_or_default()
+    }
+
+    /// Consume one item, return whether there was any
+    pub fn take(&mut self, power_up: PowerUp) -> bool {
+        match self.items.get_mut(&power_up) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Owned power-ups with their counts, empty ones excluded
+    pub fn iter(&self) -> impl Iterator<Item = (PowerUp, usize)> + '_ {
+        self.items
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(power_up, count)| (*power_up, *count))
+    }
+}
+
+impl FromIterator<(PowerUp, usize)> for Inventory {
+    fn from_iter<I: IntoIterator<Item = (PowerUp, usize)>>(iter: I) -> Self {
+        let mut inventory = Self::new();
+        iter.into_iter()
+            .for_each(|(power_up, count)| inventory.add(power_up, count));
+        inventory
+    }
+}
@@ -1 +1,6 @@
-pub use crate::{data_type::prelude::*, mapping::Mapping, tracing::*, update::*};
+pub use crate::{
+    achievement::*, bot::*, campaign::*, cursor::*, daily::*, data_type::prelude::*, hot_seat::*,
+    leaderboard::*, mapping::Mapping, net::*, power_up::*, puzzle::*, random::*, replay::*,
+    rule::*, score::*, session::*, stats::*, tracing::*, transform::*, update::*, versus::*,
+    visibility::*,
+};
@@ -0,0 +1,333 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Extra rule a puzzle must be solved under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+    /// Matches and misses allowed, all together
+    MaxMoves(usize),
+    /// No `then` tile may be matched while a `first` tile is left
+    ClearBefore { first: SpriteId, then: SpriteId },
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxMoves(moves) => write!(f, "At most {} moves, misses included", moves),
+            Self::ClearBefore { first, then } => {
+                write!(f, "Clear every #{} before any #{}", first, then)
+            }
+        }
+    }
+}
+
+/// Hand-designed board to clear completely, shuffles aren't allowed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    /// Stable name used in saves
+    pub id: String,
+    pub name: String,
+    /// One line per row, sprites separated by spaces and `.` for empty cells
+    pub layout: Vec<String>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl Puzzle {
+    pub fn new(id: &str, name: &str, layout: &[&str], constraints: Vec<Constraint>) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            layout: layout.iter().map(|row| row.to_string()).collect(),
+            constraints,
+        }
+    }
+
+    fn rows(&self) -> Result<Vec<Vec<SpriteId>>, &'static str> {
+        let rows: Vec<Vec<SpriteId>> = self
+            .layout
+            .iter()
+            .map(|row| {
+                row.split_whitespace()
+                    .map(|sprite| match sprite {
+                        "." => Ok(NO_SPRITE),
+                        sprite => sprite.parse().map_err(|_| "invalid sprite in layout"),
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        let columns = rows.first().map_or(0, Vec::len);
+        if columns == 0 || rows.iter().any(|row| row.len() != columns) {
+            return Err("layout rows must have the same number of cells");
+        }
+        if columns > u8::MAX as usize - 2 || rows.len() > u8::MAX as usize - 2 {
+            return Err("layout is too large");
+        }
+        Ok(rows)
+    }
+
+    /// Sprites of layout, each once
+    fn sprites(rows: &[Vec<SpriteId>]) -> Vec<SpriteId> {
+        let mut sprites: Vec<SpriteId> = rows
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|sprite| *sprite != NO_SPRITE)
+            .collect();
+        sprites.sort();
+        sprites.dedup();
+        sprites
+    }
+
+    /// Plain pairs matching on a board of layout size, no shuffle
+    pub fn rules(&self) -> Result<Rules, &'static str> {
+        let rows = self.rows()?;
+        let sprites = Self::sprites(&rows);
+        let tiles = rows.iter().flatten().filter(|s| **s != NO_SPRITE).count();
+        Ok(Rules {
+            columns: rows[0].len() as u8,
+            rows: rows.len() as u8,
+            tiles,
+            sprite_pool: sprites,
+            shuffles: Some(0),
+            ..Default::default()
+        })
+    }
+
+    /// Board of layout, every sprite must come in pairs
+    pub fn board(&self) -> Result<Mapping, &'static str> {
+        let rows = self.rows()?;
+        let sprites = Self::sprites(&rows);
+        let count = |sprite| rows.iter().flatten().filter(|s| **s == sprite).count();
+        if sprites.iter().any(|sprite| count(*sprite) % 2 != 0) {
+            return Err("every sprite must come in pairs");
+        }
+        let mut mapping = Mapping::new(rows[0].len() as u8, rows.len() as u8);
+        for (row, sprites) in rows.iter().enumerate() {
+            for (column, sprite) in sprites.iter().enumerate() {
+                if *sprite != NO_SPRITE {
+                    let cell = Cell {
+                        column: column + 1,
+                        row: row + 1,
+                    };
+                    mapping.fill_cell(&cell, *sprite)?;
+                }
+            }
+        }
+        Ok(mapping)
+    }
+
+    pub fn session(&self) -> Result<GameSession, &'static str> {
+        Ok(GameSession::with_board(self.rules()?, 0, self.board()?))
+    }
+}
+
+/// Built-in puzzles, easiest first
+pub fn default_puzzles() -> Vec<Puzzle> {
+    vec![
+        Puzzle::new(
+            "first_steps",
+            "First steps",
+            &["25 4 4 25", "7 1 1 7"],
+            vec![Constraint::MaxMoves(4)],
+        ),
+        Puzzle::new(
+            "crossed_wires",
+            "Crossed wires",
+            &["4 7 25 1", "4 25 1 25", "4 1 25 7", "1 7 7 4"],
+            vec![],
+        ),
+        Puzzle::new(
+            "pecking_order",
+            "Pecking order",
+            &[
+                "19 16 133 54 52 16",
+                "19 133 16 52 16 16",
+                "16 19 52 54 133 .",
+                ". 52 19 133 54 54",
+            ],
+            vec![Constraint::ClearBefore {
+                first: 133,
+                then: 52,
+            }],
+        ),
+        Puzzle::new(
+            "no_second_chance",
+            "No second chance",
+            &["52 1 1 4 4", "1 52 4 1 52", "52 4 52 4 .", "52 1 1 4 ."],
+            vec![
+                Constraint::MaxMoves(9),
+                Constraint::ClearBefore { first: 4, then: 1 },
+            ],
+        ),
+        Puzzle::new(
+            "last_stand",
+            "Last stand",
+            &[
+                ". 143 150 94 94",
+                "6 150 143 6 6",
+                "94 6 94 . 143",
+                "6 94 150 143 6",
+                "150 150 . 150 94",
+            ],
+            vec![
+                Constraint::MaxMoves(11),
+                Constraint::ClearBefore {
+                    first: 150,
+                    then: 143,
+                },
+            ],
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleState {
+    Playing,
+    /// Board cleared with every constraint met
+    Solved,
+    /// Constraint broken before board was cleared
+    Broken(Constraint),
+    /// No move left
+    Stuck,
+}
+
+/// One attempt at a puzzle, checking its constraints on every match
+pub struct PuzzleGame {
+    puzzle: Puzzle,
+    session: GameSession,
+    state: PuzzleState,
+}
+
+impl PuzzleGame {
+    pub fn new(puzzle: Puzzle) -> Result<Self, &'static str> {
+        Ok(Self {
+            session: puzzle.session()?,
+            puzzle,
+            state: PuzzleState::Playing,
+        })
+    }
+
+    pub fn puzzle(&self) -> &Puzzle {
+        &self.puzzle
+    }
+
+    pub fn session(&self) -> &GameSession {
+        &self.session
+    }
+
+    pub fn state(&self) -> PuzzleState {
+        self.state
+    }
+
+    /// Moves left under the tightest move limit, if any
+    pub fn moves_left(&self) -> Option<usize> {
+        self.puzzle
+            .constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::MaxMoves(moves) => {
+                    Some(moves.saturating_sub(self.session.stats().moves))
+                }
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Start over from the initial board
+    pub fn retry(&mut self) {
+        // Puzzle was checked when game was created
+        self.session = self.puzzle.session().unwrap();
+        self.state = PuzzleState::Playing;
+    }
+
+    /// First constraint broken by `event`
+    fn broken(&self, event: &Event) -> Option<Constraint> {
+        let mapping = self.session.mapping();
+        let moves = self.session.stats().moves;
+        self.puzzle
+            .constraints
+            .iter()
+            .copied()
+            .find(|constraint| match (constraint, event) {
+                (Constraint::MaxMoves(max), Event::Matched(_) | Event::Failed { .. }) => {
+                    moves >= *max && !mapping.is_cleared()
+                }
+                (Constraint::ClearBefore { first, then }, Event::Matched(couple)) => {
+                    couple.remnants.iter().any(|(_, sprite)| sprite == then)
+                        && mapping
+                            .mutable_cells()
+                            .any(|cell| mapping.get_sprite(&cell) == *first)
+                }
+                _ => false,
+            })
+    }
+
+    /// Drive session, undo and shuffle being ignored. Return its events.
+    pub fn update(&mut self, input: &[Action], dt: f64) -> Vec<Event> {
+        if self.state != PuzzleState::Playing {
+            return Vec::new();
+        }
+        let input: Vec<Action> = input
+            .iter()
+            .filter(|action| !matches!(action, Action::Undo | Action::Shuffle))
+            .cloned()
+            .collect();
+        let events = self.session.update(&input, dt);
+        if let Some(constraint) = events.iter().find_map(|event| self.broken(event)) {
+            self.state = PuzzleState::Broken(constraint);
+        } else {
+            match self.session.state() {
+                State::Won => self.state = PuzzleState::Solved,
+                State::Lost | State::Stuck => self.state = PuzzleState::Stuck,
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Ids of solved puzzles, kept by player
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SolvedPuzzles {
+    pub ids: Vec<String>,
+}
+
+impl SolvedPuzzles {
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.iter().any(|solved| solved == id)
+    }
+
+    /// Mark `id` solved, true if it wasn't yet
+    pub fn insert(&mut self, id: &str) -> bool {
+        if self.contains(id) {
+            return false;
+        }
+        self.ids.push(id.to_string());
+        true
+    }
+}
+
+/// One id per line
+impl fmt::Display for SolvedPuzzles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for id in self.ids.iter() {
+            writeln!(f, "{}", id)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SolvedPuzzles {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            ids: s
+                .lines()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+}
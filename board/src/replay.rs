@@ -0,0 +1,305 @@
+use crate::prelude::*;
+use std::{fmt, ops::Range, str::FromStr};
+
+/// Version written in new replays, those without one are version 1
+pub const REPLAY_VERSION: u32 = 2;
+
+/// Bring replay written by an older version up to `REPLAY_VERSION`, same as
+/// saves
+fn migrate(version: u32, replay: &str) -> Result<String, &'static str> {
+    match version {
+        REPLAY_VERSION => Ok(replay.to_string()),
+        // Checksums were not recorded yet, replay just isn't checked
+        1 => migrate(2, replay),
+        version if version > REPLAY_VERSION => Err("replay is from a newer version"),
+        _ => Err("unsupported replay version"),
+    }
+}
+
+/// Player input of one session update
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Session clock when actions were applied
+    pub time: f64,
+    /// Length of the update that applied them
+    pub dt: f64,
+    pub actions: Vec<Action>,
+}
+
+/// Everything needed to play a game again: seed, rules and player input
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub rules: Rules,
+    pub steps: Vec<Step>,
+    /// Session clock at the end of recording
+    pub duration: f64,
+    /// Session checksum by number of steps applied, every
+    /// `Replay::CHECK_INTERVAL` steps
+    pub checks: Vec<(usize, u64)>,
+}
+
+impl Replay {
+    pub fn new(rules: Rules, seed: u64) -> Self {
+        Self {
+            seed,
+            rules,
+            steps: Vec::new(),
+            duration: 0.,
+            checks: Vec::new(),
+        }
+    }
+
+    /// Steps between checksums
+    pub const CHECK_INTERVAL: usize = 10;
+
+    /// Keep input of an update starting at `time`, empty input is skipped
+    pub fn record(&mut self, time: f64, dt: f64, actions: &[Action]) {
+        if !actions.is_empty() {
+            self.steps.push(Step {
+                time,
+                dt,
+                actions: actions.to_vec(),
+            });
+        }
+    }
+
+    /// Keep `checksum` of session if latest step is due for a check
+    pub fn check(&mut self, checksum: u64) {
+        let steps = self.steps.len();
+        let recorded = self.checks.last().is_some_and(|(at, _)| *at == steps);
+        if steps > 0 && steps.is_multiple_of(Self::CHECK_INTERVAL) && !recorded {
+            self.checks.push((steps, checksum));
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Select(cell) => write!(f, "select {} {}", cell.column, cell.row),
+            Self::AlterSelection(cell) => write!(f, "alter {} {}", cell.column, cell.row),
+            Self::Pause => write!(f, "pause"),
+            Self::Shuffle => write!(f, "shuffle"),
+            Self::Hint => write!(f, "hint"),
+            Self::Undo => write!(f, "undo"),
+            Self::UsePowerUp(power_up) => write!(f, "power {}", power_up.name()),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let cell = || -> Result<Cell, &'static str> {
+            match words[1..] {
+                [column, row] => Ok(Cell {
+                    column: column.parse().map_err(|_| "invalid column")?,
+                    row: row.parse().map_err(|_| "invalid row")?,
+                }),
+                _ => Err("cell needs column and row"),
+            }
+        };
+        match words.first() {
+            Some(&"select") => Ok(Self::Select(cell()?)),
+            Some(&"alter") => Ok(Self::AlterSelection(cell()?)),
+            Some(&"pause") => Ok(Self::Pause),
+            Some(&"shuffle") => Ok(Self::Shuffle),
+            Some(&"hint") => Ok(Self::Hint),
+            Some(&"undo") => Ok(Self::Undo),
+            Some(&"power") => Ok(Self::UsePowerUp(
+                words.get(1).ok_or("missing power-up")?.parse()?,
+            )),
+            _ => Err("unknown action"),
+        }
+    }
+}
+
+/// `version`, `seed`, `duration`, `step=<time>,<dt>,<action>;<action>` and
+/// `check=<steps> <checksum>` lines, rules lines are prefixed with `rules.`
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "version={}", REPLAY_VERSION)?;
+        writeln!(f, "seed={}", self.seed)?;
+        writeln!(f, "duration={}", self.duration)?;
+        for line in self.rules.to_string().lines() {
+            writeln!(f, "rules.{}", line)?;
+        }
+        for step in self.steps.iter() {
+            let actions: Vec<String> = step.actions.iter().map(Action::to_string).collect();
+            writeln!(f, "step={},{},{}", step.time, step.dt, actions.join(";"))?;
+        }
+        for (steps, checksum) in self.checks.iter() {
+            writeln!(f, "check={} {}", steps, checksum)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let version = match s.lines().find_map(|line| line.strip_prefix("version=")) {
+            Some(version) => version
+                .trim()
+                .parse()
+                .map_err(|_| "invalid replay version")?,
+            None => 1,
+        };
+        let s = migrate(version, s)?;
+        let mut replay = Self::new(Default::default(), 0);
+        let mut rules = String::new();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            if let Some(rule) = line.strip_prefix("rules.") {
+                rules.push_str(rule);
+                rules.push('\n');
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            let value = value.trim();
+            match key.trim() {
+                "version" => {}
+                "seed" => replay.seed = value.parse().map_err(|_| "invalid seed")?,
+                "duration" => replay.duration = value.parse().map_err(|_| "invalid duration")?,
+                "step" => {
+                    let mut fields = value.splitn(3, ',');
+                    let mut number = || {
+                        fields
+                            .next()
+                            .and_then(|field| field.trim().parse::<f64>().ok())
+                            .ok_or("invalid step time")
+                    };
+                    let (time, dt) = (number()?, number()?);
+                    let actions = fields
+                        .next()
+                        .ok_or("missing step actions")?
+                        .split(';')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?;
+                    replay.steps.push(Step { time, dt, actions });
+                }
+                "check" => {
+                    let (steps, checksum) = value.split_once(' ').ok_or("invalid check")?;
+                    replay.checks.push((
+                        steps.parse().map_err(|_| "invalid check")?,
+                        checksum.trim().parse().map_err(|_| "invalid check")?,
+                    ));
+                }
+                _ => return Err("unknown key"),
+            }
+        }
+        replay.rules = rules.parse()?;
+        Ok(replay)
+    }
+}
+
+/// How fast a replay runs compared to real time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    Normal,
+    Double,
+    /// Straight to the end
+    Instant,
+}
+
+/// Session driven by a replay instead of player
+pub struct Playback {
+    replay: Replay,
+    session: GameSession,
+    /// Index of next step to apply
+    next: usize,
+    pub speed: Speed,
+    /// Steps among which session stopped matching recording
+    desync: Option<Range<usize>>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let mut session = GameSession::new(replay.rules.clone(), replay.seed);
+        session.update(&[], 0.);
+        Self {
+            replay,
+            session,
+            next: 0,
+            speed: Speed::Normal,
+            desync: None,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn session(&self) -> &GameSession {
+        &self.session
+    }
+
+    /// Indices of steps since the last matching check, one of which made
+    /// playback diverge from recording, e.g. when game logic changed since
+    /// recording
+    pub fn desync(&self) -> Option<Range<usize>> {
+        self.desync.clone()
+    }
+
+    /// Whole replay has been played
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.replay.steps.len() && self.session.clock() >= self.replay.duration
+    }
+
+    /// Play until session clock reaches `time` or game is over, return
+    /// events on the way
+    fn play_until(&mut self, time: f64) -> Vec<Event> {
+        let time = time.min(self.replay.duration);
+        let mut events = Vec::new();
+        while let Some(step) = self.replay.steps.get(self.next) {
+            if step.time > time {
+                break;
+            }
+            let (step_time, dt, actions) = (step.time, step.dt, step.actions.clone());
+            if step_time > self.session.clock() {
+                events.extend(self.session.update(&[], step_time - self.session.clock()));
+            }
+            events.extend(self.session.update(&actions, dt));
+            self.next += 1;
+            if self.desync.is_none() {
+                let checksum = self.session.checksum();
+                self.desync = self
+                    .replay
+                    .checks
+                    .iter()
+                    .find(|(steps, recorded)| *steps == self.next && *recorded != checksum)
+                    .map(|(steps, _)| steps.saturating_sub(Replay::CHECK_INTERVAL)..*steps);
+            }
+        }
+        // Clock stands still when paused or over, no point waiting for it
+        if time > self.session.clock() && self.session.state() == State::Playing {
+            events.extend(self.session.update(&[], time - self.session.clock()));
+        }
+        events
+    }
+
+    /// Advance by `dt` seconds of real time at current speed
+    pub fn update(&mut self, dt: f64) -> Vec<Event> {
+        let target = match self.speed {
+            Speed::Normal => self.session.clock() + dt,
+            Speed::Double => self.session.clock() + dt * 2.,
+            Speed::Instant => self.replay.duration,
+        };
+        self.play_until(target)
+    }
+
+    /// Jump to `time` of session clock, going back replays from the start
+    pub fn seek(&mut self, time: f64) {
+        if time < self.session.clock() {
+            *self = Self {
+                speed: self.speed,
+                desync: self.desync.clone(),
+                ..Self::new(self.replay.clone())
+            };
+        }
+        self.play_until(time);
+    }
+}
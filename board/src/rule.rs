@@ -0,0 +1,67 @@
+use crate::prelude::*;
+use std::collections::HashSet;
+
+/// Decide whether 2 sprites can be matched, regardless of path
+pub trait MatchRule {
+    fn matches(&self, first: SpriteId, second: SpriteId) -> bool;
+}
+
+/// Only the same sprite matches, the classic rule
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Identity;
+
+impl MatchRule for Identity {
+    fn matches(&self, first: SpriteId, second: SpriteId) -> bool {
+        first == second
+    }
+}
+
+/// Sprites of the same family match
+#[derive(Debug, Default, Clone)]
+pub struct Category {
+    pub table: SpriteTable,
+}
+
+impl Category {
+    pub fn new(table: SpriteTable) -> Self {
+        Self { table }
+    }
+}
+
+impl MatchRule for Category {
+    fn matches(&self, first: SpriteId, second: SpriteId) -> bool {
+        first == second || self.table.share_category(first, second)
+    }
+}
+
+/// Only explicitly listed pairs match, e.g. question and answer
+#[derive(Debug, Default, Clone)]
+pub struct PairTable {
+    pairs: HashSet<(SpriteId, SpriteId)>,
+}
+
+impl PairTable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Let `first` and `second` match each other, in any order
+    pub fn insert(&mut self, first: SpriteId, second: SpriteId) {
+        self.pairs.insert((first.min(second), first.max(second)));
+    }
+}
+
+impl FromIterator<(SpriteId, SpriteId)> for PairTable {
+    fn from_iter<I: IntoIterator<Item = (SpriteId, SpriteId)>>(iter: I) -> Self {
+        let mut table = Self::new();
+        iter.into_iter()
+            .for_each(|(first, second)| table.insert(first, second));
+        table
+    }
+}
+
+impl MatchRule for PairTable {
+    fn matches(&self, first: SpriteId, second: SpriteId) -> bool {
+        self.pairs.contains(&(first.min(second), first.max(second)))
+    }
+}
@@ -0,0 +1,194 @@
+use crate::prelude::*;
+
+/// How points are given, one set per mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreRules {
+    /// Points for each match
+    pub base: i64,
+    /// Bonus for each turn of connection
+    pub per_turn: i64,
+    /// Bonus for each cell travelled by connection
+    pub per_length: i64,
+    /// Points lost on each failed attempt
+    pub miss_penalty: i64,
+    /// Points lost on each hint
+    pub hint_penalty: i64,
+    /// Points lost on each undo, on top of reverting the undone match
+    pub undo_penalty: i64,
+    /// Matches at most this many seconds apart continue combo
+    pub combo_window: f64,
+    /// Multiplier added for each match in combo
+    pub combo_step: f64,
+    /// Upper limit of combo multiplier
+    pub max_multiplier: f64,
+}
+
+impl Default for ScoreRules {
+    fn default() -> Self {
+        Self {
+            base: 100,
+            per_turn: 20,
+            per_length: 2,
+            miss_penalty: 30,
+            hint_penalty: 50,
+            undo_penalty: 50,
+            combo_window: 3.,
+            combo_step: 0.25,
+            max_multiplier: 3.,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreReason {
+    Match { turns: usize, length: usize },
+    Miss(Failure),
+    Hint,
+    Undo,
+}
+
+/// Single change of score, for HUD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreChange {
+    pub reason: ScoreReason,
+    pub delta: i64,
+    pub total: i64,
+    /// Number of consecutive matches in current combo, 0 when combo broken
+    pub combo: usize,
+    pub multiplier: f64,
+}
+
+/// Keep score and combo by following connector events
+#[derive(Debug, Clone, Default)]
+pub struct Scoring {
+    pub rules: ScoreRules,
+    total: i64,
+    combo: usize,
+    longest_combo: usize,
+    /// Epoch of latest match
+    last_match: Option<f64>,
+    /// Previous event was a hint power-up, its hint is already paid for
+    paid_hint: bool,
+}
+
+impl Scoring {
+    pub fn new(rules: ScoreRules) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.total
+    }
+
+    pub fn combo(&self) -> usize {
+        self.combo
+    }
+
+    pub fn longest_combo(&self) -> usize {
+        self.longest_combo
+    }
+
+    /// Epoch of latest match, combo continues from it
+    pub fn last_match(&self) -> Option<f64> {
+        self.last_match
+    }
+
+    /// Resume score and combo of a saved game
+    pub(crate) fn restore(
+        &mut self,
+        total: i64,
+        combo: usize,
+        longest_combo: usize,
+        last_match: Option<f64>,
+    ) {
+        self.total = total;
+        self.combo = combo;
+        self.longest_combo = longest_combo;
+        self.last_match = last_match;
+    }
+
+    /// Next match starts a new combo
+    pub fn break_combo(&mut self) {
+        self.combo = 0;
+        self.last_match = None;
+    }
+
+    pub fn multiplier(&self) -> f64 {
+        (1. + self.combo as f64 * self.rules.combo_step).min(self.rules.max_multiplier)
+    }
+
+    fn change(&mut self, reason: ScoreReason, delta: i64) -> ScoreChange {
+        self.total += delta;
+        ScoreChange {
+            reason,
+            delta,
+            total: self.total,
+            combo: self.combo,
+            multiplier: self.multiplier(),
+        }
+    }
+
+    /// Update score from event, return changes caused by it
+    pub fn apply(&mut self, event: &Event) -> Vec<ScoreChange> {
+        let paid_hint = std::mem::replace(
+            &mut self.paid_hint,
+            matches!(event, Event::PowerUpUsed(PowerUp::Hint)),
+        );
+        match event {
+            Event::Matched(couple) => {
+                // Combo continues if previous match is recent enough
+                self.combo = match self.last_match {
+                    Some(last) if couple.epoch - last <= self.rules.combo_window => self.combo + 1,
+                    _ => 0,
+                };
+                self.longest_combo = self.longest_combo.max(self.combo + 1);
+                self.last_match = Some(couple.epoch);
+
+                let multiplier = self.multiplier();
+                let (turns, length) = (couple.turns(), couple.length());
+                let points = self.rules.base
+                    + self.rules.per_turn * turns as i64
+                    + self.rules.per_length * length as i64;
+                let mut changes = vec![self.change(
+                    ScoreReason::Match { turns, length },
+                    (points as f64 * multiplier).round() as i64,
+                )];
+
+                // Tiles blasted by bombs score as straight matches, one for
+                // each group of the size of this couple
+                let blasted: usize = couple
+                    .effects
+                    .iter()
+                    .map(|effect| match effect {
+                        Effect::Explode { cleared, .. } => cleared.len(),
+                        _ => 0,
+                    })
+                    .sum();
+                for _ in 0..blasted / couple.remnants.len().max(1) {
+                    changes.push(self.change(
+                        ScoreReason::Match {
+                            turns: 0,
+                            length: 0,
+                        },
+                        (self.rules.base as f64 * multiplier).round() as i64,
+                    ));
+                }
+                changes
+            }
+            Event::Failed { failure, .. } => {
+                self.break_combo();
+                vec![self.change(ScoreReason::Miss(*failure), -self.rules.miss_penalty)]
+            }
+            Event::Hinted(_) if paid_hint => Vec::new(),
+            Event::Hinted(_) => vec![self.change(ScoreReason::Hint, -self.rules.hint_penalty)],
+            Event::Undone => {
+                self.break_combo();
+                vec![self.change(ScoreReason::Undo, -self.rules.undo_penalty)]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,521 @@
+mod rules;
+mod save;
+
+use crate::prelude::*;
+pub use rules::*;
+pub use save::SAVE_VERSION;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    /// Board not generated yet
+    Loading,
+    Playing,
+    /// Clock stopped, selection ignored
+    Paused,
+    /// Board cleared
+    Won,
+    /// Mode rules failed, e.g. time is up
+    Lost,
+    /// Tiles left but no move available
+    Stuck,
+}
+
+impl State {
+    /// Game is over, nothing changes anymore
+    pub fn is_over(&self) -> bool {
+        matches!(self, Self::Won | Self::Lost | Self::Stuck)
+    }
+}
+
+/// What player can do to a session
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// Select tile at cell, selecting it again deselects
+    Select(Cell),
+    /// Replace latest selection with tile at cell
+    AlterSelection(Cell),
+    /// Toggle between playing and paused
+    Pause,
+    /// Shuffle remaining tiles, also way out when stuck
+    Shuffle,
+    /// Show a group that can be matched
+    Hint,
+    /// Revert latest match
+    Undo,
+    /// Consume power-up from inventory
+    UsePowerUp(PowerUp),
+}
+
+/// State before a match, to undo it
+struct Snapshot {
+    mapping: Mapping,
+    scoring: Scoring,
+    /// Power-ups are earned from match count and drawn from rng
+    matches: usize,
+    inventory: Inventory,
+    rng: Rng,
+    /// Countdown and game clock, so time bonus of match is taken back
+    timer: Option<f64>,
+    clock: f64,
+    /// Connector moves, chameleons change sprite from them
+    moves: usize,
+}
+
+/// A whole game, from board generation until it is over
+pub struct GameSession {
+    state: State,
+    rules: Rules,
+    rng: Rng,
+    mapping: Mapping,
+    connector: CellConnector,
+    visibility: Option<Visibility>,
+    scoring: Scoring,
+    /// Game time, stops when paused
+    clock: f64,
+    /// Countdown of time-attack mode
+    timer: Option<f64>,
+    /// Shuffles allowed from now on, unlimited if `None`
+    shuffles_left: Option<usize>,
+    inventory: Inventory,
+    /// Countdown stops until clock reaches this
+    frozen_until: f64,
+    /// Number of matches so far, to earn power-ups
+    matches: usize,
+    stats: GameStats,
+    /// Traces of the latest failed connection
+    failed_paths: Vec<Path>,
+    /// Events raised by session itself, not yet returned
+    events: Vec<Event>,
+    /// Group shown by latest hint, until board changes
+    hint: Option<Vec<Cell>>,
+    /// Snapshots before each match, latest last
+    history: Vec<Snapshot>,
+    /// Seed, rules and input so far
+    replay: Replay,
+    /// Fixed board played instead of a generated one
+    board: Option<Mapping>,
+}
+
+impl GameSession {
+    pub fn new(rules: Rules, seed: u64) -> Self {
+        Self {
+            state: State::Loading,
+            replay: Replay::new(rules.clone(), seed),
+            rules,
+            rng: Rng::new(seed),
+            mapping: Default::default(),
+            connector: Default::default(),
+            visibility: None,
+            scoring: Default::default(),
+            clock: 0.,
+            timer: None,
+            shuffles_left: None,
+            inventory: Default::default(),
+            frozen_until: 0.,
+            matches: 0,
+            stats: Default::default(),
+            failed_paths: Vec::new(),
+            events: Vec::new(),
+            hint: None,
+            history: Vec::new(),
+            board: None,
+        }
+    }
+
+    /// Session on fixed `board` instead of one generated from rules, e.g. a
+    /// puzzle. Its replay can't be played back.
+    pub fn with_board(rules: Rules, seed: u64, board: Mapping) -> Self {
+        Self {
+            board: Some(board),
+            ..Self::new(rules, seed)
+        }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    ///////////////////////////// View of session /////////////////////////////
+    ///////////////////////////////////////////////////////////////////////////
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn mapping(&self) -> &Mapping {
+        &self.mapping
+    }
+
+    pub fn connector(&self) -> &CellConnector {
+        &self.connector
+    }
+
+    pub fn visibility(&self) -> Option<&Visibility> {
+        self.visibility.as_ref()
+    }
+
+    pub fn scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
+    /// Game time, same clock as `Couple::epoch`
+    pub fn clock(&self) -> f64 {
+        self.clock
+    }
+
+    /// Time left before losing, if mode has a time limit
+    pub fn time_left(&self) -> Option<f64> {
+        self.timer
+    }
+
+    /// Shuffles allowed from now on, unlimited if `None`
+    pub fn shuffles_left(&self) -> Option<usize> {
+        self.shuffles_left
+    }
+
+    /// Numbers of this game so far, final once game is over
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    /// Whether countdown is stopped by freeze power-up
+    pub fn is_frozen(&self) -> bool {
+        self.clock < self.frozen_until
+    }
+
+    /// Group shown by latest hint, until board changes
+    pub fn hint(&self) -> Option<&[Cell]> {
+        self.hint.as_deref()
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.is_empty()
+    }
+
+    pub fn failed_paths(&self) -> &[Path] {
+        &self.failed_paths
+    }
+
+    /// Recording of this game so far, to play it again with `Playback`
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Hash of board, random generator and score. Sessions fed the same
+    /// input have the same checksum, a difference means they diverged.
+    pub fn checksum(&self) -> u64 {
+        let mix = |value: u64| Rng::new(value).next_u64();
+        self.mapping.hash()
+            ^ mix(self.rng.state())
+            ^ mix(self.scoring.total() as u64).rotate_left(1)
+    }
+
+    /// Register observer of board events, kept for the whole game
+    pub fn subscribe(&mut self, listener: SharedListener) -> ListenerId {
+        self.connector.subscribe(listener)
+    }
+
+    pub fn unsubscribe(&mut self, id: ListenerId) {
+        self.connector.unsubscribe(id);
+    }
+
+    /// Hand board to `player`, dropping selection and combo of previous one
+    pub fn set_player(&mut self, player: PlayerId) {
+        self.connector.clear_selection();
+        self.scoring.break_combo();
+        self.connector.set_player(player);
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    ///////////////////////////////// Update //////////////////////////////////
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Generate board and start playing
+    fn start(&mut self) {
+        self.mapping = match &self.board {
+            Some(board) => board.clone(),
+            None => self.rules.generate(&mut self.rng),
+        };
+        let mut connector = CellConnector::with_group_size(self.rules.group_size).unwrap();
+        connector.take_listeners(&mut self.connector);
+        self.connector = connector;
+        self.connector
+            .set_boxed_rule(self.rules.matching.to_rule(&self.rules.sprites()));
+        self.visibility = self
+            .rules
+            .face_down
+            .map(|duration| Visibility::new(&self.mapping, duration));
+        self.scoring = Scoring::new(self.rules.scoring.clone());
+        self.clock = 0.;
+        self.timer = self.rules.time_limit;
+        self.shuffles_left = self.rules.shuffles;
+        self.inventory = self.rules.power_ups.iter().copied().collect();
+        self.frozen_until = 0.;
+        self.matches = 0;
+        self.stats = Default::default();
+        self.failed_paths.clear();
+        self.hint = None;
+        self.history.clear();
+        self.state = State::Playing;
+        self.check_board();
+    }
+
+    /// Won if board is cleared, stuck if no move left, lost if stuck without
+    /// shuffle left
+    fn check_board(&mut self) {
+        if self.mapping.is_cleared() {
+            self.state = State::Won;
+        } else if self.connector.available_moves(&self.mapping).is_empty() {
+            self.state = if self.shuffles_left == Some(0) {
+                State::Lost
+            } else {
+                State::Stuck
+            };
+        } else if self.state == State::Stuck {
+            self.state = State::Playing;
+        }
+    }
+
+    /// Add `delta` seconds to countdown, if any
+    fn adjust_timer(&mut self, delta: f64) {
+        if let Some(timer) = self.timer.as_mut() {
+            *timer = (*timer + delta).max(0.);
+        }
+    }
+
+    fn shuffle(&mut self) {
+        match self.shuffles_left.as_mut() {
+            Some(0) => return,
+            Some(shuffles_left) => *shuffles_left -= 1,
+            None => {}
+        }
+        self.adjust_timer(-self.rules.shuffle_cost);
+        self.shuffle_board();
+    }
+
+    fn shuffle_board(&mut self) {
+        self.connector.clear_selection();
+        self.hint = None;
+        self.mapping.shuffle(&mut self.rng);
+        self.events.push(Event::Shuffled);
+        self.check_board();
+    }
+
+    /// Rotate, mirror or transpose board along with selection, destroying
+    /// couples and face-down timers. Not an action, so it isn't recorded in
+    /// replay.
+    pub fn transform(&mut self, transform: Transform) {
+        // Connector remaps its cells from old board, before it changes
+        self.connector.transform(&self.mapping, transform);
+        self.mapping.transform(transform);
+        if let Some(visibility) = self.visibility.as_mut() {
+            visibility.transform(transform);
+        }
+        // Snapshots and traces refer to old positions
+        self.history.clear();
+        self.failed_paths.clear();
+        self.hint = None;
+    }
+
+    fn use_power_up(&mut self, power_up: PowerUp) {
+        if !self.inventory.take(power_up) {
+            return;
+        }
+        // Undo would give used power-up back
+        self.history.clear();
+        // Comes before events of its effect, so scoring knows a hint is paid
+        self.events.push(Event::PowerUpUsed(power_up));
+        match power_up {
+            PowerUp::Shuffle => self.shuffle_board(),
+            PowerUp::Hint => self.show_hint(),
+            PowerUp::Bomb => {
+                self.connector.ignore_next_path();
+                // Let player pick a group even without any path left
+                if self.state == State::Stuck {
+                    self.state = State::Playing;
+                }
+            }
+            PowerUp::Freeze => self.frozen_until = self.clock + self.rules.freeze_duration,
+            PowerUp::Rotate => self.transform(Transform::Rotate90),
+        }
+    }
+
+    /// Give a random power-up every `earn_every` matches
+    fn earn_power_ups(&mut self, matches: usize) {
+        let Some(earn_every) = self.rules.earn_every.filter(|every| *every > 0) else {
+            self.matches += matches;
+            return;
+        };
+        for _ in 0..matches {
+            self.matches += 1;
+            if self.matches.is_multiple_of(earn_every) {
+                let power_up = PowerUp::ALL[self.rng.gen_range(0, PowerUp::ALL.len())];
+                self.inventory.add(power_up, 1);
+                self.events.push(Event::PowerUpEarned(power_up));
+            }
+        }
+    }
+
+    fn show_hint(&mut self) {
+        if let Some(group) = self
+            .connector
+            .available_moves(&self.mapping)
+            .into_iter()
+            .next()
+        {
+            self.hint = Some(group.clone());
+            self.events.push(Event::Hinted(group));
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(snapshot) = self.history.pop() {
+            self.mapping = snapshot.mapping;
+            self.scoring = snapshot.scoring;
+            self.matches = snapshot.matches;
+            self.inventory = snapshot.inventory;
+            self.rng = snapshot.rng;
+            // Time spent since match still counts
+            let elapsed = self.clock - snapshot.clock;
+            self.timer = snapshot.timer.map(|timer| (timer - elapsed).max(0.));
+            self.connector.set_moves(snapshot.moves);
+            self.connector.clear_selection();
+            self.hint = None;
+            self.failed_paths.clear();
+            self.events.push(Event::Undone);
+            self.check_board();
+        }
+    }
+
+    fn apply(&mut self, action: &Action) {
+        match (self.state, action) {
+            (State::Playing, Action::Select(cell)) => {
+                if !self.mapping.check_valid_cell(cell)
+                    || self.mapping.get_sprite(cell) == NO_SPRITE
+                {
+                    return;
+                }
+                self.connector.select(*cell);
+                if let Some(visibility) = self.visibility.as_mut() {
+                    visibility.reveal(cell, self.clock);
+                }
+            }
+            (State::Playing, Action::AlterSelection(cell)) => {
+                if !self.mapping.check_valid_cell(cell)
+                    || self.mapping.get_sprite(cell) == NO_SPRITE
+                {
+                    return;
+                }
+                self.connector.alter_selection(*cell);
+                if let Some(visibility) = self.visibility.as_mut() {
+                    visibility.reveal(cell, self.clock);
+                }
+            }
+            (State::Playing | State::Stuck, Action::Shuffle) => self.shuffle(),
+            (State::Playing, Action::Hint) => self.show_hint(),
+            (State::Playing | State::Stuck, Action::Undo) => self.undo(),
+            (State::Playing | State::Stuck, Action::UsePowerUp(power_up)) => {
+                self.use_power_up(*power_up)
+            }
+            (State::Playing, Action::Pause) => self.state = State::Paused,
+            (State::Paused, Action::Pause) => self.state = State::Playing,
+            _ => {}
+        }
+    }
+
+    /// Apply player input and advance game by `dt` seconds, return what
+    /// happened
+    pub fn update(&mut self, input: &[Action], dt: f64) -> Vec<Event> {
+        let previous_state = self.state;
+        if self.state == State::Loading {
+            self.start();
+        }
+
+        self.replay.record(self.clock, dt, input);
+        input.iter().for_each(|action| self.apply(action));
+
+        let mut snapshot = None;
+        if self.state == State::Playing {
+            // Only time past freeze counts, whatever the length of updates
+            let frozen = (self.frozen_until - self.clock).clamp(0., dt);
+            self.adjust_timer(frozen - dt);
+            self.clock += dt;
+
+            // Keep state before a possible match
+            if self.connector.get_selections().len() == self.rules.group_size {
+                snapshot = Some(Snapshot {
+                    mapping: self.mapping.clone(),
+                    scoring: self.scoring.clone(),
+                    matches: self.matches,
+                    inventory: self.inventory.clone(),
+                    rng: self.rng.clone(),
+                    timer: self.timer,
+                    clock: self.clock,
+                    moves: self.connector.moves(),
+                });
+            }
+            if let Err(conquered) = self.connector.update(&mut self.mapping, self.clock) {
+                self.failed_paths = conquered;
+            }
+            if let Some(visibility) = self.visibility.as_mut() {
+                visibility.update(self.clock);
+            }
+        }
+
+        let mut events = std::mem::take(&mut self.events);
+        let connector_events = self.connector.drain_events();
+        if let Some(direction) = self.rules.gravity {
+            if connector_events
+                .iter()
+                .any(|event| matches!(event, Event::Matched(_)))
+            {
+                self.mapping.collapse(direction);
+            }
+        }
+        if connector_events
+            .iter()
+            .any(|event| matches!(event, Event::Matched(_) | Event::Effect(_)))
+        {
+            self.check_board();
+        }
+        if connector_events
+            .iter()
+            .any(|event| matches!(event, Event::Matched(_)))
+        {
+            self.hint = None;
+            self.history.extend(snapshot);
+        }
+        let matches = connector_events
+            .iter()
+            .filter(|event| matches!(event, Event::Matched(_)))
+            .count();
+        self.adjust_timer(self.rules.time_bonus * matches as f64);
+        events.extend(connector_events);
+        self.earn_power_ups(matches);
+        events.append(&mut self.events);
+        let score_changes: Vec<Event> = events
+            .iter()
+            .flat_map(|event| self.scoring.apply(event))
+            .map(Event::Score)
+            .collect();
+        events.extend(score_changes);
+        if self.state == State::Playing && self.time_left() == Some(0.) {
+            self.state = State::Lost;
+        }
+        if self.state != previous_state {
+            events.push(Event::StateChanged(self.state));
+        }
+        events.iter().for_each(|event| self.stats.record(event));
+        self.stats.sync(self.state, self.clock);
+        self.replay.duration = self.clock;
+        if !input.is_empty() {
+            self.replay.check(self.checksum());
+        }
+        events
+    }
+}
@@ -0,0 +1,383 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Which sprites can be matched together, see `MatchRule`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Matching {
+    /// Only the same sprite
+    Identity,
+    /// Consecutive sprites form families of `size` sprites
+    Families { size: usize },
+    /// Explicit pairs only
+    Pairs(Vec<(SpriteId, SpriteId)>),
+}
+
+impl Matching {
+    /// Build the rule for board using `sprites`
+    pub fn to_rule(&self, sprites: &[SpriteId]) -> Box<dyn MatchRule> {
+        match self {
+            Self::Identity => Box::new(Identity),
+            Self::Families { size } => {
+                let mut sprites = sprites.to_vec();
+                sprites.sort();
+                sprites.dedup();
+                Box::new(Category::new(SpriteTable::chunked(
+                    sprites.into_iter(),
+                    *size,
+                )))
+            }
+            Self::Pairs(pairs) => Box::new(pairs.iter().copied().collect::<PairTable>()),
+        }
+    }
+}
+
+/// Everything that makes a game mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    /// Playable columns, border excluded
+    pub columns: u8,
+    /// Playable rows, border excluded
+    pub rows: u8,
+    /// Number of tiles placed on board, rounded down to complete groups
+    pub tiles: usize,
+    /// Number of tiles to select for a match
+    pub group_size: usize,
+    pub matching: Matching,
+    /// Sprites to pick from, cycled when there are more groups than sprites.
+    /// Empty means sprites counting from 1.
+    pub sprite_pool: Vec<SpriteId>,
+    /// Special tiles put on random tiles
+    pub specials: Vec<Special>,
+    /// Direction remaining tiles slide to after each match
+    pub gravity: Option<Direction>,
+    /// Tiles start face-down and stay revealed for this long when selected
+    pub face_down: Option<f64>,
+    /// Countdown at start, game is lost when it reaches zero
+    pub time_limit: Option<f64>,
+    /// Seconds added to countdown on each match
+    pub time_bonus: f64,
+    /// Seconds taken from countdown on each shuffle
+    pub shuffle_cost: f64,
+    /// Number of shuffles allowed, unlimited if `None`
+    pub shuffles: Option<usize>,
+    /// Power-ups given at start
+    pub power_ups: Vec<(PowerUp, usize)>,
+    /// A random power-up is earned every this many matches
+    pub earn_every: Option<usize>,
+    /// Seconds countdown stops with freeze power-up
+    pub freeze_duration: f64,
+    pub scoring: ScoreRules,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            columns: 16,
+            rows: 16,
+            tiles: 64,
+            group_size: 2,
+            matching: Matching::Identity,
+            sprite_pool: Vec::new(),
+            specials: Vec::new(),
+            gravity: None,
+            face_down: None,
+            time_limit: None,
+            time_bonus: 0.,
+            shuffle_cost: 0.,
+            shuffles: None,
+            power_ups: Vec::new(),
+            earn_every: None,
+            freeze_duration: 10.,
+            scoring: Default::default(),
+        }
+    }
+}
+
+impl Rules {
+    /// Sprite of each group on generated board, in order of placement
+    pub fn sprites(&self) -> Vec<SpriteId> {
+        let groups = self.tiles / self.group_size;
+        if self.sprite_pool.is_empty() {
+            (1..=groups).collect()
+        } else {
+            self.sprite_pool
+                .iter()
+                .copied()
+                .cycle()
+                .take(groups)
+                .collect()
+        }
+    }
+
+    /// Generate board for these rules, all randomness comes from `rng`
+    pub fn generate(&self, rng: &mut Rng) -> Mapping {
+        let mut mapping = Mapping::new(self.columns, self.rows);
+        let mut fillable_cells: Vec<Cell> = mapping
+            .mutable_cells()
+            .filter(|cell| mapping.check_fillable_cell(cell).is_ok())
+            .collect();
+        let mut shuffled = Vec::new();
+        for _ in 0..(self.tiles / self.group_size * self.group_size) {
+            if fillable_cells.is_empty() {
+                break;
+            }
+            let idx = rng.gen_range(0, fillable_cells.len());
+            let cell = fillable_cells.remove(idx);
+            shuffled.push(cell);
+        }
+        let mut occupied = shuffled.clone();
+        mapping
+            .fill_groups(&mut shuffled, self.sprites().into_iter(), self.group_size)
+            .unwrap();
+        // Cells past the last complete group stay empty
+        occupied.retain(|cell| mapping.get_sprite(cell) != NO_SPRITE);
+
+        for special in self.specials.iter() {
+            if occupied.is_empty() {
+                break;
+            }
+            let idx = rng.gen_range(0, occupied.len());
+            let cell = occupied.remove(idx);
+            mapping.set_special(&cell, *special).unwrap();
+        }
+        mapping
+    }
+}
+
+/// Write optional value as `none` when absent
+pub(super) fn write_option<T: fmt::Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+pub(super) fn parse_option<T: FromStr>(value: &str) -> Result<Option<T>, &'static str> {
+    match value {
+        "none" => Ok(None),
+        value => value.parse().map(Some).map_err(|_| "invalid value"),
+    }
+}
+
+fn parse_direction(value: &str) -> Result<Direction, &'static str> {
+    match value {
+        "up" => Ok(Direction::Up),
+        "down" => Ok(Direction::Down),
+        "left" => Ok(Direction::Left),
+        "right" => Ok(Direction::Right),
+        _ => Err("invalid direction"),
+    }
+}
+
+fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Left => "left",
+        Direction::Right => "right",
+    }
+}
+
+pub(super) fn parse_special(value: &str) -> Result<Special, &'static str> {
+    match value.split_once(':') {
+        None if value == "wildcard" => Ok(Special::Wildcard),
+        None if value == "bomb" => Ok(Special::Bomb),
+        Some(("chameleon", period)) => Ok(Special::Chameleon {
+            period: period.parse().map_err(|_| "invalid period")?,
+        }),
+        _ => Err("invalid special"),
+    }
+}
+
+pub(super) fn special_name(special: &Special) -> String {
+    match special {
+        Special::Wildcard => "wildcard".to_string(),
+        Special::Bomb => "bomb".to_string(),
+        Special::Chameleon { period } => format!("chameleon:{}", period),
+    }
+}
+
+/// Power-ups with counts as `<name>:<count>` separated by spaces
+pub(super) fn write_power_ups(items: impl Iterator<Item = (PowerUp, usize)>) -> String {
+    items
+        .map(|(power_up, count)| format!("{}:{}", power_up.name(), count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(super) fn parse_power_ups(value: &str) -> Result<Vec<(PowerUp, usize)>, &'static str> {
+    value
+        .split_whitespace()
+        .map(|item| {
+            let (name, count) = item.split_once(':').ok_or("invalid power-up")?;
+            Ok((
+                name.parse()?,
+                count.parse().map_err(|_| "invalid power-up count")?,
+            ))
+        })
+        .collect()
+}
+
+impl fmt::Display for Matching {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Identity => write!(f, "identity"),
+            Self::Families { size } => write!(f, "families {}", size),
+            Self::Pairs(pairs) => {
+                write!(f, "pairs")?;
+                for (a, b) in pairs.iter() {
+                    write!(f, " {}:{}", a, b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Matching {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        match words.next() {
+            Some("identity") => Ok(Self::Identity),
+            Some("families") => Ok(Self::Families {
+                size: words
+                    .next()
+                    .and_then(|size| size.parse().ok())
+                    .ok_or("invalid family size")?,
+            }),
+            Some("pairs") => words
+                .map(|pair| {
+                    let (a, b) = pair.split_once(':').ok_or("invalid pair")?;
+                    Ok((
+                        a.parse().map_err(|_| "invalid sprite")?,
+                        b.parse().map_err(|_| "invalid sprite")?,
+                    ))
+                })
+                .collect::<Result<_, _>>()
+                .map(Self::Pairs),
+            _ => Err("invalid matching"),
+        }
+    }
+}
+
+/// One `key=value` line per field, scoring fields prefixed with `score.`
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |items: Vec<String>| items.join(" ");
+        writeln!(f, "columns={}", self.columns)?;
+        writeln!(f, "rows={}", self.rows)?;
+        writeln!(f, "tiles={}", self.tiles)?;
+        writeln!(f, "group_size={}", self.group_size)?;
+        writeln!(f, "matching={}", self.matching)?;
+        writeln!(
+            f,
+            "sprite_pool={}",
+            join(self.sprite_pool.iter().map(|id| id.to_string()).collect())
+        )?;
+        writeln!(
+            f,
+            "specials={}",
+            join(self.specials.iter().map(special_name).collect())
+        )?;
+        writeln!(
+            f,
+            "gravity={}",
+            self.gravity.map(direction_name).unwrap_or("none")
+        )?;
+        writeln!(f, "face_down={}", write_option(&self.face_down))?;
+        writeln!(f, "time_limit={}", write_option(&self.time_limit))?;
+        writeln!(f, "time_bonus={}", self.time_bonus)?;
+        writeln!(f, "shuffle_cost={}", self.shuffle_cost)?;
+        writeln!(f, "shuffles={}", write_option(&self.shuffles))?;
+        writeln!(
+            f,
+            "power_ups={}",
+            write_power_ups(self.power_ups.iter().copied())
+        )?;
+        writeln!(f, "earn_every={}", write_option(&self.earn_every))?;
+        writeln!(f, "freeze_duration={}", self.freeze_duration)?;
+        let scoring = &self.scoring;
+        writeln!(f, "score.base={}", scoring.base)?;
+        writeln!(f, "score.per_turn={}", scoring.per_turn)?;
+        writeln!(f, "score.per_length={}", scoring.per_length)?;
+        writeln!(f, "score.miss_penalty={}", scoring.miss_penalty)?;
+        writeln!(f, "score.hint_penalty={}", scoring.hint_penalty)?;
+        writeln!(f, "score.undo_penalty={}", scoring.undo_penalty)?;
+        writeln!(f, "score.combo_window={}", scoring.combo_window)?;
+        writeln!(f, "score.combo_step={}", scoring.combo_step)?;
+        writeln!(f, "score.max_multiplier={}", scoring.max_multiplier)
+    }
+}
+
+/// Missing keys keep their default value
+impl FromStr for Rules {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = Self::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            let value = value.trim();
+            let number = || value.parse::<f64>().map_err(|_| "invalid number");
+            let count = || value.parse::<usize>().map_err(|_| "invalid count");
+            let points = || value.parse::<i64>().map_err(|_| "invalid points");
+            let scoring = &mut rules.scoring;
+            match key.trim() {
+                "columns" => rules.columns = value.parse().map_err(|_| "invalid columns")?,
+                "rows" => rules.rows = value.parse().map_err(|_| "invalid rows")?,
+                "tiles" => rules.tiles = count()?,
+                "group_size" => rules.group_size = count()?,
+                "matching" => rules.matching = value.parse()?,
+                "sprite_pool" => {
+                    rules.sprite_pool = value
+                        .split_whitespace()
+                        .map(|id| id.parse().map_err(|_| "invalid sprite"))
+                        .collect::<Result<_, _>>()?
+                }
+                "specials" => {
+                    rules.specials = value
+                        .split_whitespace()
+                        .map(parse_special)
+                        .collect::<Result<_, _>>()?
+                }
+                "gravity" => {
+                    rules.gravity = match value {
+                        "none" => None,
+                        value => Some(parse_direction(value)?),
+                    }
+                }
+                "face_down" => rules.face_down = parse_option(value)?,
+                "time_limit" => rules.time_limit = parse_option(value)?,
+                "time_bonus" => rules.time_bonus = number()?,
+                "shuffle_cost" => rules.shuffle_cost = number()?,
+                "shuffles" => rules.shuffles = parse_option(value)?,
+                "power_ups" => rules.power_ups = parse_power_ups(value)?,
+                "earn_every" => rules.earn_every = parse_option(value)?,
+                "freeze_duration" => rules.freeze_duration = number()?,
+                "score.base" => scoring.base = points()?,
+                "score.per_turn" => scoring.per_turn = points()?,
+                "score.per_length" => scoring.per_length = points()?,
+                // Written by older versions, blasted tiles now score as matches
+                "score.per_cleared" => {}
+                "score.miss_penalty" => scoring.miss_penalty = points()?,
+                "score.hint_penalty" => scoring.hint_penalty = points()?,
+                "score.undo_penalty" => scoring.undo_penalty = points()?,
+                "score.combo_window" => scoring.combo_window = number()?,
+                "score.combo_step" => scoring.combo_step = number()?,
+                "score.max_multiplier" => scoring.max_multiplier = number()?,
+                _ => return Err("unknown key"),
+            }
+        }
+        // Rules may come from a file or another player, board must be
+        // playable before a session starts on it
+        if !(2..=MAX_GROUP_SIZE).contains(&rules.group_size) {
+            return Err("group size out of range");
+        }
+        if rules.columns == 0 || rules.rows == 0 {
+            return Err("board needs at least one column and one row");
+        }
+        Ok(rules)
+    }
+}
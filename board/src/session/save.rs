@@ -0,0 +1,306 @@
+//! Text format of an in-progress session. Undo history, hint and animations
+//! are not kept, face-down tiles are hidden again on resume.
+
+use super::rules::{
+    parse_option, parse_power_ups, parse_special, special_name, write_option, write_power_ups,
+};
+use super::*;
+
+/// Version written in new saves
+pub const SAVE_VERSION: u32 = 2;
+
+/// Bring save written by an older version up to `SAVE_VERSION`. Each format
+/// change bumps the version and adds a step rewriting the previous one.
+fn migrate(version: u32, saved: &str) -> Result<String, &'static str> {
+    match version {
+        SAVE_VERSION => Ok(saved.to_string()),
+        // Board was always held by first player before `player` was saved
+        1 => migrate(2, &format!("{}\nplayer=0\n", saved.trim_end())),
+        version if version > SAVE_VERSION => Err("save is from a newer version"),
+        _ => Err("unsupported save version"),
+    }
+}
+
+fn state_name(state: State) -> &'static str {
+    match state {
+        State::Loading => "loading",
+        State::Playing => "playing",
+        State::Paused => "paused",
+        State::Won => "won",
+        State::Lost => "lost",
+        State::Stuck => "stuck",
+    }
+}
+
+fn parse_state(value: &str) -> Result<State, &'static str> {
+    match value {
+        "loading" => Ok(State::Loading),
+        "playing" => Ok(State::Playing),
+        "paused" => Ok(State::Paused),
+        "won" => Ok(State::Won),
+        "lost" => Ok(State::Lost),
+        "stuck" => Ok(State::Stuck),
+        _ => Err("invalid state"),
+    }
+}
+
+fn parse_cell(value: &str) -> Result<Cell, &'static str> {
+    let (column, row) = value.split_once(' ').ok_or("cell needs column and row")?;
+    Ok(Cell {
+        column: column.trim().parse().map_err(|_| "invalid column")?,
+        row: row.trim().parse().map_err(|_| "invalid row")?,
+    })
+}
+
+fn join<T: ToString>(items: impl Iterator<Item = T>, separator: &str) -> String {
+    items
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl GameSession {
+    /// Write session as `key=value` lines, to resume it with
+    /// `GameSession::load`
+    pub fn save(&self) -> String {
+        let mut lines = vec![
+            format!("version={}", SAVE_VERSION),
+            format!("state={}", state_name(self.state)),
+            format!("rng={}", self.rng.state()),
+            format!("clock={}", self.clock),
+            format!("timer={}", write_option(&self.timer)),
+            format!("shuffles_left={}", write_option(&self.shuffles_left)),
+            format!("inventory={}", write_power_ups(self.inventory.iter())),
+            format!("frozen_until={}", self.frozen_until),
+            format!("matches={}", self.matches),
+            format!("score.total={}", self.scoring.total()),
+            format!("score.combo={}", self.scoring.combo()),
+            format!("score.longest_combo={}", self.scoring.longest_combo()),
+            format!(
+                "score.last_match={}",
+                write_option(&self.scoring.last_match())
+            ),
+            format!(
+                "selection={}",
+                join(
+                    self.connector
+                        .get_selections()
+                        .iter()
+                        .map(|cell| format!("{} {}", cell.column, cell.row)),
+                    ";"
+                )
+            ),
+            format!("moves={}", self.connector.moves()),
+            format!("player={}", self.connector.player()),
+            format!("ignore_path={}", self.connector.is_ignoring_path()),
+        ];
+
+        // Playable cells only, border is always empty
+        let mapping = &self.mapping;
+        lines.push(format!(
+            "mapping.size={} {}",
+            mapping.columns - 2,
+            mapping.rows - 2
+        ));
+        for row in 1..mapping.rows - 1 {
+            lines.push(format!(
+                "mapping.row={}",
+                join(
+                    (1..mapping.columns - 1)
+                        .map(|column| mapping.get_sprite(&Cell { column, row })),
+                    " "
+                )
+            ));
+        }
+        let mut specials: Vec<(&Cell, &Special)> = mapping.specials().collect();
+        specials.sort_by_key(|(cell, _)| **cell);
+        for (cell, special) in specials {
+            lines.push(format!(
+                "mapping.special={} {} {}",
+                cell.column,
+                cell.row,
+                special_name(special)
+            ));
+        }
+
+        let stats = &self.stats;
+        lines.extend([
+            format!("stats.moves={}", stats.moves),
+            format!("stats.matches={}", stats.matches),
+            format!("stats.failed={}", stats.failed),
+            format!("stats.hints={}", stats.hints),
+            format!("stats.shuffles={}", stats.shuffles),
+            format!("stats.undos={}", stats.undos),
+            format!("stats.power_ups={}", stats.power_ups),
+            format!("stats.longest_combo={}", stats.longest_combo),
+            format!("stats.match_times={}", join(stats.match_times.iter(), " ")),
+            format!("stats.last_match={}", stats.last_match),
+        ]);
+
+        // Replay carries seed and rules
+        lines.extend(
+            self.replay
+                .to_string()
+                .lines()
+                .map(|line| format!("replay.{}", line)),
+        );
+        lines.join("\n") + "\n"
+    }
+
+    /// Resume session written by `GameSession::save`, a game in progress
+    /// comes back paused
+    pub fn load(saved: &str) -> Result<Self, &'static str> {
+        let mut session = Self::load_snapshot(saved)?;
+        if session.state == State::Playing {
+            session.state = State::Paused;
+            // So replay pauses too, and is resumed by the next pause
+            session.replay.record(session.clock, 0., &[Action::Pause]);
+        }
+        Ok(session)
+    }
+
+    /// Same as `GameSession::load` but game keeps running, to catch up with
+    /// an authoritative copy
+    pub fn load_snapshot(saved: &str) -> Result<Self, &'static str> {
+        let version = saved
+            .lines()
+            .find_map(|line| line.strip_prefix("version="))
+            .ok_or("missing save version")?
+            .trim()
+            .parse()
+            .map_err(|_| "invalid save version")?;
+        let saved = migrate(version, saved)?;
+
+        let replay: Replay = saved
+            .lines()
+            .filter_map(|line| line.strip_prefix("replay."))
+            .map(|line| format!("{}\n", line))
+            .collect::<String>()
+            .parse()?;
+        let mut session = Self::new(replay.rules.clone(), replay.seed);
+        session.start();
+        session.replay = replay;
+
+        let mut state = State::Playing;
+        let mut score = (0, 0, 0, None);
+        let mut size = None;
+        let mut rows: Vec<Vec<SpriteId>> = Vec::new();
+        let mut specials = Vec::new();
+        let mut selection = Vec::new();
+        for line in saved.lines().filter(|line| !line.trim().is_empty()) {
+            if line.starts_with("replay.") {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            let value = value.trim();
+            let number = || value.parse::<f64>().map_err(|_| "invalid number");
+            let count = || value.parse::<usize>().map_err(|_| "invalid count");
+            let stats = &mut session.stats;
+            match key.trim() {
+                "version" => {}
+                "state" => state = parse_state(value)?,
+                "rng" => session.rng = Rng::new(value.parse().map_err(|_| "invalid rng")?),
+                "clock" => session.clock = number()?,
+                "timer" => session.timer = parse_option(value)?,
+                "shuffles_left" => session.shuffles_left = parse_option(value)?,
+                "inventory" => session.inventory = parse_power_ups(value)?.into_iter().collect(),
+                "frozen_until" => session.frozen_until = number()?,
+                "matches" => session.matches = count()?,
+                "score.total" => score.0 = value.parse().map_err(|_| "invalid score")?,
+                "score.combo" => score.1 = count()?,
+                "score.longest_combo" => score.2 = count()?,
+                "score.last_match" => score.3 = parse_option(value)?,
+                "selection" => {
+                    selection = value
+                        .split(';')
+                        .filter(|cell| !cell.trim().is_empty())
+                        .map(parse_cell)
+                        .collect::<Result<_, _>>()?
+                }
+                "moves" => session.connector.set_moves(count()?),
+                "player" => session.connector.set_player(count()?),
+                "ignore_path" => {
+                    if value.parse().map_err(|_| "invalid flag")? {
+                        session.connector.ignore_next_path();
+                    }
+                }
+                "mapping.size" => {
+                    let (columns, rows) = value.split_once(' ').ok_or("invalid size")?;
+                    size = Some((
+                        columns.parse::<u8>().map_err(|_| "invalid size")?,
+                        rows.parse::<u8>().map_err(|_| "invalid size")?,
+                    ));
+                }
+                "mapping.row" => rows.push(
+                    value
+                        .split_whitespace()
+                        .map(|id| id.parse().map_err(|_| "invalid sprite"))
+                        .collect::<Result<_, _>>()?,
+                ),
+                "mapping.special" => {
+                    let (cell, special) = value.rsplit_once(' ').ok_or("invalid special")?;
+                    specials.push((parse_cell(cell)?, parse_special(special)?));
+                }
+                "stats.moves" => stats.moves = count()?,
+                "stats.matches" => stats.matches = count()?,
+                "stats.failed" => stats.failed = count()?,
+                "stats.hints" => stats.hints = count()?,
+                "stats.shuffles" => stats.shuffles = count()?,
+                "stats.undos" => stats.undos = count()?,
+                "stats.power_ups" => stats.power_ups = count()?,
+                "stats.longest_combo" => stats.longest_combo = count()?,
+                "stats.match_times" => {
+                    stats.match_times = value
+                        .split_whitespace()
+                        .map(|time| time.parse().map_err(|_| "invalid time"))
+                        .collect::<Result<_, _>>()?
+                }
+                "stats.last_match" => stats.last_match = number()?,
+                _ => return Err("unknown key"),
+            }
+        }
+
+        let (columns, rows_count) = size.ok_or("missing board size")?;
+        if rows.len() != rows_count as usize || rows.iter().any(|row| row.len() != columns as usize)
+        {
+            return Err("board rows don't match board size");
+        }
+        // Rotated board has columns and rows swapped
+        let rules = (session.rules.columns, session.rules.rows);
+        if (columns, rows_count) != rules && (rows_count, columns) != rules {
+            return Err("board size doesn't match rules");
+        }
+        let mut mapping = Mapping::new(columns, rows_count);
+        for (row, sprites) in rows.iter().enumerate() {
+            for (column, sprite) in sprites.iter().enumerate() {
+                if *sprite != NO_SPRITE {
+                    let cell = Cell {
+                        column: column + 1,
+                        row: row + 1,
+                    };
+                    mapping.fill_cell(&cell, *sprite)?;
+                }
+            }
+        }
+        for (cell, special) in specials {
+            if !mapping.check_valid_cell(&cell) {
+                return Err("special outside board");
+            }
+            mapping.set_special(&cell, special)?;
+        }
+        session.mapping = mapping;
+        session.visibility = session
+            .rules
+            .face_down
+            .map(|duration| Visibility::new(&session.mapping, duration));
+        session.scoring.restore(score.0, score.1, score.2, score.3);
+        for cell in selection {
+            if session.mapping.check_valid_cell(&cell) {
+                session.connector.select(cell);
+            }
+        }
+        session.connector.drain_events();
+        session.state = state;
+        Ok(session)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::prelude::*;
+use std::{fmt, str::FromStr};
+
+/// Numbers of a single game, collected from session events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameStats {
+    /// Attempted groups, matched or not
+    pub moves: usize,
+    pub matches: usize,
+    pub failed: usize,
+    pub hints: usize,
+    pub shuffles: usize,
+    pub undos: usize,
+    pub power_ups: usize,
+    pub longest_combo: usize,
+    /// Seconds between consecutive matches, first one counted from start
+    pub match_times: Vec<f64>,
+    pub total_time: f64,
+    pub score: i64,
+    pub won: bool,
+    /// Epoch of latest match
+    pub(crate) last_match: f64,
+}
+
+impl GameStats {
+    pub fn average_match_time(&self) -> Option<f64> {
+        (!self.match_times.is_empty())
+            .then(|| self.match_times.iter().sum::<f64>() / self.match_times.len() as f64)
+    }
+
+    /// Update numbers from event
+    pub fn record(&mut self, event: &Event) {
+        match event {
+            Event::Matched(couple) => {
+                self.moves += 1;
+                self.matches += 1;
+                self.match_times.push(couple.epoch - self.last_match);
+                self.last_match = couple.epoch;
+            }
+            Event::Failed { .. } => {
+                self.moves += 1;
+                self.failed += 1;
+            }
+            Event::Hinted(_) => self.hints += 1,
+            Event::Shuffled => self.shuffles += 1,
+            Event::Undone => self.undos += 1,
+            Event::Score(change) => {
+                self.score = change.total;
+                if let ScoreReason::Match { .. } = change.reason {
+                    self.longest_combo = self.longest_combo.max(change.combo + 1);
+                }
+            }
+            _ => {}
+        }
+        if let Event::PowerUpUsed(_) = event {
+            self.power_ups += 1;
+        }
+    }
+
+    /// Follow session clock and state, numbers are final once game is over
+    pub fn sync(&mut self, state: State, clock: f64) {
+        self.won = state == State::Won;
+        self.total_time = clock;
+    }
+}
+
+/// Short record of a finished game, kept for trends
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameSummary {
+    pub score: i64,
+    pub time: f64,
+    pub moves: usize,
+    pub failed: usize,
+    pub won: bool,
+}
+
+impl From<&GameStats> for GameSummary {
+    fn from(stats: &GameStats) -> Self {
+        Self {
+            score: stats.score,
+            time: stats.total_time,
+            moves: stats.moves,
+            failed: stats.failed,
+            won: stats.won,
+        }
+    }
+}
+
+/// Totals over all games of a profile
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LifetimeStats {
+    pub games: usize,
+    pub wins: usize,
+    pub moves: usize,
+    pub matches: usize,
+    pub failed: usize,
+    pub hints: usize,
+    pub shuffles: usize,
+    pub longest_combo: usize,
+    pub total_time: f64,
+    pub total_score: i64,
+    pub best_score: i64,
+    /// Latest games, oldest first
+    pub recent: Vec<GameSummary>,
+}
+
+impl LifetimeStats {
+    /// Number of games kept in `recent`
+    pub const RECENT_GAMES: usize = 20;
+
+    pub fn add(&mut self, stats: &GameStats) {
+        self.games += 1;
+        self.wins += stats.won as usize;
+        self.moves += stats.moves;
+        self.matches += stats.matches;
+        self.failed += stats.failed;
+        self.hints += stats.hints;
+        self.shuffles += stats.shuffles;
+        self.longest_combo = self.longest_combo.max(stats.longest_combo);
+        self.total_time += stats.total_time;
+        self.total_score += stats.score;
+        self.best_score = if self.games == 1 {
+            stats.score
+        } else {
+            self.best_score.max(stats.score)
+        };
+        self.recent.push(stats.into());
+        if self.recent.len() > Self::RECENT_GAMES {
+            self.recent.remove(0);
+        }
+    }
+
+    pub fn average_score(&self) -> Option<f64> {
+        (self.games > 0).then(|| self.total_score as f64 / self.games as f64)
+    }
+
+    pub fn average_time(&self) -> Option<f64> {
+        (self.games > 0).then(|| self.total_time / self.games as f64)
+    }
+
+    /// Share of attempts that were matches
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.moves > 0).then(|| self.matches as f64 / self.moves as f64)
+    }
+
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.games > 0).then(|| self.wins as f64 / self.games as f64)
+    }
+
+    /// Average score of newer half of recent games minus older half, positive
+    /// when player is improving
+    pub fn score_trend(&self) -> Option<f64> {
+        if self.recent.len() < 2 {
+            return None;
+        }
+        let (older, newer) = self.recent.split_at(self.recent.len() / 2);
+        let average = |games: &[GameSummary]| {
+            games.iter().map(|game| game.score as f64).sum::<f64>() / games.len() as f64
+        };
+        Some(average(newer) - average(older))
+    }
+}
+
+impl fmt::Display for LifetimeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "games={}", self.games)?;
+        writeln!(f, "wins={}", self.wins)?;
+        writeln!(f, "moves={}", self.moves)?;
+        writeln!(f, "matches={}", self.matches)?;
+        writeln!(f, "failed={}", self.failed)?;
+        writeln!(f, "hints={}", self.hints)?;
+        writeln!(f, "shuffles={}", self.shuffles)?;
+        writeln!(f, "longest_combo={}", self.longest_combo)?;
+        writeln!(f, "total_time={}", self.total_time)?;
+        writeln!(f, "total_score={}", self.total_score)?;
+        writeln!(f, "best_score={}", self.best_score)?;
+        for game in self.recent.iter() {
+            writeln!(
+                f,
+                "game={},{},{},{},{}",
+                game.score, game.time, game.moves, game.failed, game.won
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameSummary {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split(',').map(str::trim).collect();
+        if fields.len() != 5 {
+            return Err("game summary needs 5 fields");
+        }
+        Ok(Self {
+            score: fields[0].parse().map_err(|_| "invalid score")?,
+            time: fields[1].parse().map_err(|_| "invalid time")?,
+            moves: fields[2].parse().map_err(|_| "invalid moves")?,
+            failed: fields[3].parse().map_err(|_| "invalid failed")?,
+            won: fields[4].parse().map_err(|_| "invalid won")?,
+        })
+    }
+}
+
+impl FromStr for LifetimeStats {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut stats = Self::default();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (key, value) = line.split_once('=').ok_or("missing `=` in line")?;
+            let value = value.trim();
+            let count = || value.parse::<usize>().map_err(|_| "invalid count");
+            match key.trim() {
+                "games" => stats.games = count()?,
+                "wins" => stats.wins = count()?,
+                "moves" => stats.moves = count()?,
+                "matches" => stats.matches = count()?,
+                "failed" => stats.failed = count()?,
+                "hints" => stats.hints = count()?,
+                "shuffles" => stats.shuffles = count()?,
+                "longest_combo" => stats.longest_combo = count()?,
+                "total_time" => stats.total_time = value.parse().map_err(|_| "invalid time")?,
+                "total_score" => stats.total_score = value.parse().map_err(|_| "invalid score")?,
+                "best_score" => stats.best_score = value.parse().map_err(|_| "invalid score")?,
+                "game" => stats.recent.push(value.parse()?),
+                _ => return Err("unknown key"),
+            }
+        }
+        Ok(stats)
+    }
+}
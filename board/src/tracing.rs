@@ -16,10 +16,10 @@ impl Path {
     /// The direction of path is not guaranteed.
     fn axis_reflection(&self) -> RangeInclusive<usize> {
         match self.direction {
-            Direction::Up => ((self.origin.row - self.free_cells)..=(self.origin.row - 1)),
-            Direction::Down => ((self.origin.row + 1)..=(self.origin.row + self.free_cells)),
-            Direction::Left => ((self.origin.column - self.free_cells)..=(self.origin.column - 1)),
-            Direction::Right => ((self.origin.column + 1)..=(self.origin.column + self.free_cells)),
+            Direction::Up => (self.origin.row - self.free_cells)..=(self.origin.row - 1),
+            Direction::Down => (self.origin.row + 1)..=(self.origin.row + self.free_cells),
+            Direction::Left => (self.origin.column - self.free_cells)..=(self.origin.column - 1),
+            Direction::Right => (self.origin.column + 1)..=(self.origin.column + self.free_cells),
         }
     }
 
@@ -30,17 +30,17 @@ impl Path {
 
     /// Check if 2 paths is on same line
     fn same_line(&self, other: &Self) -> bool {
-        match (
-            self.direction.axis(),
-            other.direction.axis(),
-            self.origin.column == other.origin.column,
-            self.origin.row == other.origin.row,
-        ) {
-            (_, _, true, true) => true,
-            (Axis::Column, Axis::Column, true, _) => true,
-            (Axis::Row, Axis::Row, _, true) => true,
-            _ => false,
-        }
+        matches!(
+            (
+                self.direction.axis(),
+                other.direction.axis(),
+                self.origin.column == other.origin.column,
+                self.origin.row == other.origin.row,
+            ),
+            (_, _, true, true)
+                | (Axis::Column, Axis::Column, true, _)
+                | (Axis::Row, Axis::Row, _, true)
+        )
     }
 
     /// Check if overlap.
@@ -166,8 +166,8 @@ impl RayCast {
             })
             .min_by_key(|(first_pole, second_pole)| {
                 // Take the bridge with minimum travel path
-                ((self.origin.distance_sqr(&first_pole) as f32).sqrt()
-                    + (first_pole.distance_sqr(&second_pole) as f32).sqrt()
+                ((self.origin.distance_sqr(first_pole) as f32).sqrt()
+                    + (first_pole.distance_sqr(second_pole) as f32).sqrt()
                     + (second_pole.distance_sqr(&other.origin) as f32).sqrt())
                     as usize
             })
@@ -204,6 +204,25 @@ impl Mapping {
         }
     }
 
+    /// Find connection between traces, labelled with kind of connection
+    fn find_connection(
+        &self,
+        first_trace: &RayCast,
+        second_trace: &RayCast,
+    ) -> Option<(&'static str, Vec<Cell>)> {
+        if let Some(connection) = first_trace.find_overlap(second_trace) {
+            Some(("Overlap connect", connection))
+        } else if let Some(connection) = first_trace.find_crossed(second_trace) {
+            Some(("Crossed connect", connection))
+        } else {
+            first_trace
+                .find_bridge(second_trace, |cell| {
+                    self.check_valid_cell(cell) && self.get_sprite(cell) == NO_SPRITE
+                })
+                .map(|connection| ("Bridge connect", connection))
+        }
+    }
+
     pub fn connect(
         &self,
         first_cell: &Cell,
@@ -212,16 +231,7 @@ impl Mapping {
         let first_trace = self.ray_cast(first_cell);
         let second_trace = self.ray_cast(second_cell);
 
-        if let Some(connection) = first_trace.find_overlap(&second_trace) {
-            println!("Overlap connect");
-            return Ok(connection);
-        } else if let Some(connection) = first_trace.find_crossed(&second_trace) {
-            println!("Crossed connect");
-            return Ok(connection);
-        } else if let Some(connection) = first_trace.find_bridge(&second_trace, |cell| {
-            self.check_valid_cell(cell) && self.get_sprite(cell) == NO_SPRITE
-        }) {
-            println!("Bridge connect");
+        if let Some((_, connection)) = self.find_connection(&first_trace, &second_trace) {
             return Ok(connection);
         }
 
@@ -229,8 +239,44 @@ impl Mapping {
         Err(("Cannot connect", paths))
     }
 
-    pub fn no_more_move(&self) -> bool {
-        /// TODO: Integrate raytracing
-        false
+    /// Same as `connect`, without collecting traces
+    pub fn find_path(&self, first_cell: &Cell, second_cell: &Cell) -> Option<Vec<Cell>> {
+        self.find_connection(&self.ray_cast(first_cell), &self.ray_cast(second_cell))
+            .map(|(_, connection)| connection)
+    }
+
+    pub fn can_connect(&self, first_cell: &Cell, second_cell: &Cell) -> bool {
+        self.find_path(first_cell, second_cell).is_some()
+    }
+
+    /// All pairs of tiles that match under `rule` and can be connected.
+    /// Wildcards match any sprite.
+    pub fn available_moves(&self, rule: &dyn MatchRule) -> Vec<(Cell, Cell)> {
+        let occupied: Vec<Cell> = self
+            .mutable_cells()
+            .filter(|cell| self.get_sprite(cell) != NO_SPRITE)
+            .collect();
+        let is_wildcard = |cell: &Cell| self.get_special(cell) == Some(Special::Wildcard);
+
+        occupied
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, first)| {
+                occupied[idx + 1..]
+                    .iter()
+                    .map(move |second| (*first, *second))
+            })
+            .filter(|(first, second)| {
+                is_wildcard(first)
+                    || is_wildcard(second)
+                    || rule.matches(self.get_sprite(first), self.get_sprite(second))
+            })
+            .filter(|(first, second)| self.can_connect(first, second))
+            .collect()
+    }
+
+    /// No pair matching under `rule` can be connected
+    pub fn no_more_move(&self, rule: &dyn MatchRule) -> bool {
+        self.available_moves(rule).is_empty()
     }
 }
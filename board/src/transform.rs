@@ -0,0 +1,219 @@
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Rotate clockwise by 90 degrees
+    Rotate90,
+    /// Rotate by 180 degrees
+    Rotate180,
+    /// Rotate clockwise by 270 degrees (counter-clockwise by 90 degrees)
+    Rotate270,
+    /// Mirror left to right
+    MirrorHorizontal,
+    /// Mirror top to bottom
+    MirrorVertical,
+    /// Swap columns and rows
+    Transpose,
+}
+
+impl Transform {
+    pub const ALL: [Self; 6] = [
+        Self::Rotate90,
+        Self::Rotate180,
+        Self::Rotate270,
+        Self::MirrorHorizontal,
+        Self::MirrorVertical,
+        Self::Transpose,
+    ];
+
+    /// Whether columns and rows are swapped after transform
+    pub fn swaps_axes(&self) -> bool {
+        matches!(self, Self::Rotate90 | Self::Rotate270 | Self::Transpose)
+    }
+
+    /// The transform that undo this one
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Rotate90 => Self::Rotate270,
+            Self::Rotate270 => Self::Rotate90,
+            other => *other,
+        }
+    }
+
+    /// Size of board after transform
+    pub fn dimensions(&self, columns: usize, rows: usize) -> (usize, usize) {
+        if self.swaps_axes() {
+            (rows, columns)
+        } else {
+            (columns, rows)
+        }
+    }
+
+    /// Map cell on a board of `columns` x `rows` (border included) to its
+    /// position on the transformed board
+    pub fn apply(&self, cell: &Cell, columns: usize, rows: usize) -> Cell {
+        let last_column = columns - 1;
+        let last_row = rows - 1;
+        let (column, row) = match self {
+            Self::Rotate90 => (last_row - cell.row, cell.column),
+            Self::Rotate180 => (last_column - cell.column, last_row - cell.row),
+            Self::Rotate270 => (cell.row, last_column - cell.column),
+            Self::MirrorHorizontal => (last_column - cell.column, cell.row),
+            Self::MirrorVertical => (cell.column, last_row - cell.row),
+            Self::Transpose => (cell.row, cell.column),
+        };
+        Cell { column, row }
+    }
+
+    /// Map direction of travel to its direction on the transformed board
+    pub fn apply_direction(&self, direction: &Direction) -> Direction {
+        use Direction::*;
+        match (self, direction) {
+            (Self::Rotate90, Up) => Right,
+            (Self::Rotate90, Right) => Down,
+            (Self::Rotate90, Down) => Left,
+            (Self::Rotate90, Left) => Up,
+            (Self::Rotate180, _) => {
+                Self::Rotate90.apply_direction(&Self::Rotate90.apply_direction(direction))
+            }
+            (Self::Rotate270, _) => {
+                Self::Rotate180.apply_direction(&Self::Rotate90.apply_direction(direction))
+            }
+            (Self::MirrorHorizontal, Left) => Right,
+            (Self::MirrorHorizontal, Right) => Left,
+            (Self::MirrorVertical, Up) => Down,
+            (Self::MirrorVertical, Down) => Up,
+            (Self::Transpose, Up) => Left,
+            (Self::Transpose, Left) => Up,
+            (Self::Transpose, Down) => Right,
+            (Self::Transpose, Right) => Down,
+            (_, other) => *other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pair of `1` at opposite corners, walled in so it only connects around
+    /// the border
+    fn board() -> (Mapping, Cell, Cell) {
+        let mut mapping = Mapping::new(4, 3);
+        let first = Cell { column: 1, row: 1 };
+        let second = Cell { column: 4, row: 3 };
+        mapping.fill_cell(&first, 1).unwrap();
+        mapping.fill_cell(&second, 1).unwrap();
+        for (column, row) in [(2, 1), (1, 2), (2, 2), (3, 3)] {
+            mapping.fill_cell(&Cell { column, row }, 2).unwrap();
+        }
+        mapping
+            .set_special(&Cell { column: 2, row: 2 }, Special::Bomb)
+            .unwrap();
+        (mapping, first, second)
+    }
+
+    /// Connect `first` and `second` through a connector, return the couple
+    fn connect(mapping: &mut Mapping, first: Cell, second: Cell) -> Option<Couple> {
+        let mut connector = CellConnector::default();
+        connector.select(first);
+        connector.select(second);
+        connector.update(mapping, 0.).ok()?;
+        connector
+            .drain_events()
+            .into_iter()
+            .find_map(|event| match event {
+                Event::Matched(couple) => Some(couple),
+                _ => None,
+            })
+    }
+
+    fn cells(mapping: &Mapping) -> Vec<(Cell, SpriteId, Option<Special>)> {
+        mapping
+            .mutable_cells()
+            .map(|cell| (cell, mapping.get_sprite(&cell), mapping.get_special(&cell)))
+            .collect()
+    }
+
+    #[test]
+    fn connectable_pair_stays_connectable() {
+        let (mut mapping, first, second) = board();
+        let original = connect(&mut mapping.clone(), first, second).unwrap();
+        assert_eq!(original.turns(), 2);
+        for transform in Transform::ALL {
+            let first = mapping.transform_cell(transform, &first);
+            let second = mapping.transform_cell(transform, &second);
+            let mut transformed = mapping.clone();
+            transformed.transform(transform);
+            let couple = connect(&mut transformed, first, second)
+                .unwrap_or_else(|| panic!("{:?} broke connection", transform));
+            assert_eq!(couple.turns(), original.turns(), "{:?}", transform);
+            assert_eq!(couple.length(), original.length(), "{:?}", transform);
+            assert_eq!(transformed.get_sprite(&first), NO_SPRITE, "{:?}", transform);
+        }
+        // Pair is gone from original board too
+        connect(&mut mapping, first, second).unwrap();
+        assert_eq!(mapping.get_sprite(&first), NO_SPRITE);
+    }
+
+    #[test]
+    fn inverse_gives_back_original() {
+        let (mapping, first, _) = board();
+        let (columns, rows) = (6, 5);
+        for transform in Transform::ALL {
+            let mut transformed = mapping.clone();
+            transformed.transform(transform);
+            transformed.transform(transform.inverse());
+            assert_eq!(cells(&transformed), cells(&mapping), "{:?}", transform);
+            assert_eq!(transformed.hash(), mapping.hash(), "{:?}", transform);
+
+            let (new_columns, new_rows) = transform.dimensions(columns, rows);
+            let cell = transform.apply(&first, columns, rows);
+            assert_eq!(
+                transform.inverse().apply(&cell, new_columns, new_rows),
+                first,
+                "{:?}",
+                transform
+            );
+            for direction in [
+                Direction::Up,
+                Direction::Right,
+                Direction::Down,
+                Direction::Left,
+            ] {
+                let turned = transform.apply_direction(&direction);
+                assert_eq!(transform.inverse().apply_direction(&turned), direction);
+            }
+        }
+    }
+
+    #[test]
+    fn connector_selection_follows_transform() {
+        let (mapping, first, second) = board();
+        for transform in Transform::ALL {
+            let mut transformed = mapping.clone();
+            let mut connector = CellConnector::default();
+            connector.select(first);
+            connector.transform(&transformed, transform);
+            let moved = transformed.transform_cell(transform, &first);
+            transformed.transform(transform);
+            assert_eq!(connector.get_selections(), vec![&moved], "{:?}", transform);
+
+            connector.transform(&transformed, transform.inverse());
+            transformed.transform(transform.inverse());
+            assert_eq!(connector.get_selections(), vec![&first], "{:?}", transform);
+            connector.select(second);
+            assert!(
+                connector.update(&mut transformed, 0.).is_ok(),
+                "{:?}",
+                transform
+            );
+            assert_eq!(
+                transformed.get_sprite(&second),
+                NO_SPRITE,
+                "{:?}",
+                transform
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use crate::prelude::*;
+
+/// Outcome of special tiles, resolved by `CellConnector`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Wildcard at `cell` stood in for `sprite`. Tiles left of its own
+    /// sprite were turned into `sprite`, so both still come in complete groups.
+    Wildcard {
+        cell: Cell,
+        sprite: SpriteId,
+        rebalanced: Vec<Cell>,
+    },
+    /// Bomb at `origin` cleared its neighbours, each counted as a match
+    Explode {
+        origin: Cell,
+        cleared: Vec<(Cell, SpriteId)>,
+    },
+    /// Chameleon at `cell` changed its sprite by swapping with `partner`
+    Camouflage {
+        cell: Cell,
+        partner: Cell,
+        from: SpriteId,
+        to: SpriteId,
+    },
+}
+
+/// Why a selected group was not matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Sprites don't match under current rule
+    Mismatch,
+    /// No valid path between tiles
+    NoPath,
+}
+
+/// Things happened during `CellConnector::update`, for rendering and scoring
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Tile added to selection
+    Selected(Cell),
+    /// Tile removed from selection by player
+    Deselected(Cell),
+    /// Couple removed from mapping, along with effects it triggered
+    Matched(Couple),
+    /// Selected group was dropped without match
+    Failed {
+        cells: Vec<Cell>,
+        failure: Failure,
+        epoch: f64,
+    },
+    /// Effect not tied to a match
+    Effect(Effect),
+    /// Last tile removed from mapping
+    BoardCleared,
+    /// Remaining tiles were shuffled
+    Shuffled,
+    /// Player asked for a hint, showing a group that can be matched
+    Hinted(Vec<Cell>),
+    /// Latest match was reverted
+    Undone,
+    /// Power-up added to inventory
+    PowerUpEarned(PowerUp),
+    /// Power-up consumed
+    PowerUpUsed(PowerUp),
+    /// Session moved to another state
+    StateChanged(State),
+    /// Score changed
+    Score(ScoreChange),
+    /// Turn went to another player in hot-seat mode
+    TurnPassed(PlayerId),
+    /// Last level of campaign cleared
+    CampaignFinished,
+}
@@ -0,0 +1,44 @@
+use crate::prelude::*;
+use std::{cell::RefCell, rc::Rc};
+
+/// Observer of `CellConnector`, every hook does nothing by default
+pub trait ConnectorListener {
+    fn on_select(&mut self, _cell: &Cell) {}
+
+    fn on_deselect(&mut self, _cell: &Cell) {}
+
+    fn on_match(&mut self, _couple: &Couple) {}
+
+    fn on_fail(&mut self, _cells: &[Cell], _failure: Failure) {}
+
+    fn on_effect(&mut self, _effect: &Effect) {}
+
+    fn on_board_cleared(&mut self) {}
+}
+
+/// Listener shared between connector and its owner, so owner can still read
+/// what listener collected
+pub type SharedListener = Rc<RefCell<dyn ConnectorListener>>;
+
+/// Handle to unsubscribe listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerId(pub(crate) usize);
+
+/// Forward connector event to matching hook
+pub(crate) fn dispatch(listener: &mut dyn ConnectorListener, event: &Event) {
+    match event {
+        Event::Selected(cell) => listener.on_select(cell),
+        Event::Deselected(cell) => listener.on_deselect(cell),
+        Event::Matched(couple) => {
+            listener.on_match(couple);
+            couple
+                .effects
+                .iter()
+                .for_each(|effect| listener.on_effect(effect));
+        }
+        Event::Failed { cells, failure, .. } => listener.on_fail(cells, *failure),
+        Event::Effect(effect) => listener.on_effect(effect),
+        Event::BoardCleared => listener.on_board_cleared(),
+        _ => {}
+    }
+}
@@ -1,12 +1,50 @@
+mod event;
+mod listener;
+
 use crate::prelude::*;
+pub use event::*;
+pub use listener::*;
+use std::collections::HashMap;
+
+/// Index of player in multiplayer modes, always 0 in single player
+pub type PlayerId = usize;
 
+#[derive(Debug, Clone)]
 pub struct Couple {
-    /// Still need to render removed cells on destroy
-    pub remnants: [(Cell, SpriteId); 2],
+    /// Still need to render removed cells on destroy, one per tile in the
+    /// group
+    pub remnants: Vec<(Cell, SpriteId)>,
     /// Nodes for connection
     pub nodes: Vec<Cell>,
     /// Time added
     pub epoch: f64,
+    /// Effects triggered by special tiles in this couple
+    pub effects: Vec<Effect>,
+    /// Player who made the match
+    pub player: PlayerId,
+}
+
+impl Couple {
+    /// Number of direction changes along connection
+    pub fn turns(&self) -> usize {
+        let directions: Vec<Direction> = self
+            .nodes
+            .windows(2)
+            .filter_map(|pair| pair[0].direction_to(&pair[1]))
+            .collect();
+        directions
+            .windows(2)
+            .filter(|pair| pair[0] != pair[1])
+            .count()
+    }
+
+    /// Number of cells travelled along connection
+    pub fn length(&self) -> usize {
+        self.nodes
+            .windows(2)
+            .map(|pair| pair[0].column.abs_diff(pair[1].column) + pair[0].row.abs_diff(pair[1].row))
+            .sum()
+    }
 }
 
 macro_rules! define_buf {
@@ -17,6 +55,7 @@ macro_rules! define_buf {
             buf: [Option<$t>; $size],
         }
 
+        #[allow(dead_code)]
         impl $st {
             /// Auto discard old value
             fn push(&mut self, item: $t) {
@@ -49,19 +88,58 @@ macro_rules! define_buf {
             fn poll(&self) -> &[Option<$t>] {
                 &self.buf
             }
+
+            /// Modify all values in buffer in place
+            fn for_each_mut(&mut self, f: impl FnMut(&mut $t)) {
+                self.buf.iter_mut().flatten().for_each(f);
+            }
         }
     };
 }
 
-define_buf!(SelectBuf, Cell, 2);
+/// Largest group of tiles to be matched at once
+pub const MAX_GROUP_SIZE: usize = 4;
+
+define_buf!(SelectBuf, Cell, MAX_GROUP_SIZE);
 define_buf!(DestroyBuf, Couple, 3);
 
-#[derive(Default)]
 pub struct CellConnector {
-    /// Buffer for selected pair
+    /// Buffer for selected group
     select_buf: SelectBuf,
+    /// Number of tiles to select for a match
+    group_size: usize,
+    /// Which sprites can be matched together
+    rule: Box<dyn MatchRule>,
     /// Buffer for destroying pairs
     destroy_buf: DestroyBuf,
+    /// Number of attempted groups
+    moves: usize,
+    /// Next matching group is cleared without checking path
+    ignore_path: bool,
+    /// Player making the selection
+    player: PlayerId,
+    /// Events not yet drained
+    events: Vec<Event>,
+    /// Observers notified of every event, with their ids
+    listeners: Vec<(ListenerId, SharedListener)>,
+    next_listener_id: usize,
+}
+
+impl Default for CellConnector {
+    fn default() -> Self {
+        Self {
+            select_buf: Default::default(),
+            group_size: 2,
+            rule: Box::new(Identity),
+            destroy_buf: Default::default(),
+            moves: 0,
+            ignore_path: false,
+            player: 0,
+            events: Vec::new(),
+            listeners: Vec::new(),
+            next_listener_id: 0,
+        }
+    }
 }
 
 impl CellConnector {
@@ -69,76 +147,492 @@ impl CellConnector {
         Default::default()
     }
 
+    /// Connector matching groups of `group_size` tiles instead of pairs
+    pub fn with_group_size(group_size: usize) -> Result<Self, &'static str> {
+        if !(2..=MAX_GROUP_SIZE).contains(&group_size) {
+            return Err("group size out of range");
+        }
+        Ok(Self {
+            group_size,
+            ..Default::default()
+        })
+    }
+
+    pub fn group_size(&self) -> usize {
+        self.group_size
+    }
+
+    /// Replace the rule deciding which sprites match, `Identity` by default
+    pub fn set_rule(&mut self, rule: impl MatchRule + 'static) {
+        self.rule = Box::new(rule);
+    }
+
+    /// Same as `set_rule`, for rule chosen at runtime
+    pub fn set_boxed_rule(&mut self, rule: Box<dyn MatchRule>) {
+        self.rule = rule;
+    }
+
+    /// Register observer notified of every event from now on
+    pub fn subscribe(&mut self, listener: SharedListener) -> ListenerId {
+        let id = ListenerId(self.next_listener_id);
+        self.next_listener_id += 1;
+        self.listeners.push((id, listener));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: ListenerId) {
+        self.listeners.retain(|(listener_id, _)| *listener_id != id);
+    }
+
+    /// Move listeners of `other` over, keeping their ids
+    pub(crate) fn take_listeners(&mut self, other: &mut CellConnector) {
+        self.listeners = std::mem::take(&mut other.listeners);
+        self.next_listener_id = other.next_listener_id;
+    }
+
+    /// Queue event and notify listeners
+    fn emit(&mut self, event: Event) {
+        for (_, listener) in self.listeners.iter() {
+            dispatch(&mut *listener.borrow_mut(), &event);
+        }
+        self.events.push(event);
+    }
+
     pub fn select(&mut self, cell: Cell) {
         if let Some(&prev) = self.select_buf.latest() {
             // Deselect case
             if prev == cell {
-                self.select_buf.clear();
+                self.clear_selection();
                 return;
             }
         }
+        // Already in group
+        if self.select_buf.poll().contains(&Some(cell)) {
+            return;
+        }
         self.select_buf.push(cell);
+        self.emit(Event::Selected(cell));
     }
 
     pub fn alter_selection(&mut self, cell: Cell) {
+        if let Some(&prev) = self.select_buf.latest() {
+            self.emit(Event::Deselected(prev));
+        }
         self.select_buf.alternate(cell);
+        self.emit(Event::Selected(cell));
+    }
+
+    pub fn clear_selection(&mut self) {
+        let deselected: Vec<Cell> = self.get_selections().into_iter().copied().collect();
+        self.select_buf.clear();
+        deselected
+            .into_iter()
+            .for_each(|cell| self.emit(Event::Deselected(cell)));
+    }
+
+    /// Clear next matching group regardless of path
+    pub fn ignore_next_path(&mut self) {
+        self.ignore_path = true;
+    }
+
+    pub fn is_ignoring_path(&self) -> bool {
+        self.ignore_path
+    }
+
+    /// Credit next matches to `player`
+    pub fn set_player(&mut self, player: PlayerId) {
+        self.player = player;
+    }
+
+    pub fn player(&self) -> PlayerId {
+        self.player
+    }
+
+    /// Number of attempted groups, drives chameleons
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Resume move count of a saved game or an undone match
+    pub(crate) fn set_moves(&mut self, moves: usize) {
+        self.moves = moves;
     }
 
     /// Update selection and send matching couple to destroy buffer
     pub fn update(&mut self, mapping: &mut Mapping, instant: f64) -> Result<(), Vec<Path>> {
-        let selection: Vec<&Cell> = self
+        let selection: Vec<Cell> = self
             .select_buf
             .poll()
             .iter()
             .filter_map(|o| o.as_ref())
+            .copied()
             .collect();
 
-        if selection.len() < 2 {
+        if selection.len() < self.group_size {
             return Ok(());
         }
 
-        // Clear selection if not the same sprite
-        if mapping.get_sprite(selection[0]) != mapping.get_sprite(selection[1]) {
+        self.moves += 1;
+
+        // Clear selection if sprites don't match
+        if !self.is_matching(mapping, &selection) {
+            self.emit(Event::Failed {
+                cells: selection,
+                failure: Failure::Mismatch,
+                epoch: instant,
+            });
             self.select_buf.clear();
+            self.camouflage(mapping);
             return Ok(());
         }
 
-        // Connecting
-        let result = match mapping.connect(selection[0], selection[1]).map(|nodes| {
-            let remnants = [
-                (*selection[0], mapping.get_sprite(selection[0])),
-                (*selection[1], mapping.get_sprite(selection[1])),
-            ];
+        // Connecting, each consecutive pair in the group must connect unless
+        // path is ignored for this match
+        let connection = if self.ignore_path {
+            self.ignore_path = false;
+            Ok(selection.clone())
+        } else {
+            selection
+                .windows(2)
+                .map(|pair| mapping.connect(&pair[0], &pair[1]))
+                .try_fold(Vec::new(), |mut nodes: Vec<Cell>, connected| {
+                    let mut path = connected?;
+                    if nodes.last() == path.first() {
+                        path.remove(0);
+                    }
+                    nodes.append(&mut path);
+                    Ok(nodes)
+                })
+        };
+        let result = match connection.map(|nodes| {
+            let remnants = selection
+                .iter()
+                .map(|cell| (*cell, mapping.get_sprite(cell)))
+                .collect();
             Couple {
                 remnants,
                 nodes,
                 epoch: instant,
+                effects: Vec::new(),
+                player: self.player,
             }
         }) {
-            Ok(couple) => {
+            Ok(mut couple) => {
                 // Remove couple from mapping
-                mapping.clear_cell(selection[0]);
-                mapping.clear_cell(selection[1]);
+                couple.effects = Self::resolve_match(
+                    mapping,
+                    &couple.remnants,
+                    self.group_size,
+                    self.rule.as_ref(),
+                );
 
                 // Add couple to destroy buffer
+                self.emit(Event::Matched(couple.clone()));
                 self.destroy_buf.push(couple);
+                if mapping.is_cleared() {
+                    self.emit(Event::BoardCleared);
+                }
                 Ok(())
             }
-            Err((e, conquered)) => {
-                dbg!(e);
+            Err((_, conquered)) => {
+                self.emit(Event::Failed {
+                    cells: selection,
+                    failure: Failure::NoPath,
+                    epoch: instant,
+                });
                 Err(conquered)
             }
         };
         // clear selection after match
         self.select_buf.clear();
+        self.camouflage(mapping);
 
         result
     }
 
+    /// Whether all sprites of `cells` match each other under current rule,
+    /// wildcards match anything
+    fn is_matching(&self, mapping: &Mapping, cells: &[Cell]) -> bool {
+        let sprites: Vec<SpriteId> = cells
+            .iter()
+            .filter(|cell| mapping.get_special(cell) != Some(Special::Wildcard))
+            .map(|cell| mapping.get_sprite(cell))
+            .collect();
+        sprites.iter().enumerate().all(|(idx, first)| {
+            sprites[idx + 1..]
+                .iter()
+                .all(|second| self.rule.matches(*first, *second))
+        })
+    }
+
+    /// Check without side effect whether `cells` would match if selected in
+    /// order, return the connection on success. Useful for live preview.
+    pub fn preview(&self, mapping: &Mapping, cells: &[Cell]) -> Result<Vec<Cell>, Failure> {
+        if !self.is_matching(mapping, cells) {
+            return Err(Failure::Mismatch);
+        }
+        cells.windows(2).try_fold(Vec::new(), |mut nodes, pair| {
+            let mut path = mapping
+                .find_path(&pair[0], &pair[1])
+                .ok_or(Failure::NoPath)?;
+            if nodes.last() == path.first() {
+                path.remove(0);
+            }
+            nodes.append(&mut path);
+            Ok(nodes)
+        })
+    }
+
+    /// Clear matched couple from mapping and resolve effects of its special
+    /// tiles
+    fn resolve_match(
+        mapping: &mut Mapping,
+        remnants: &[(Cell, SpriteId)],
+        group_size: usize,
+        rule: &dyn MatchRule,
+    ) -> Vec<Effect> {
+        let specials: Vec<Option<Special>> = remnants
+            .iter()
+            .map(|(cell, _)| mapping.get_special(cell))
+            .collect();
+        remnants
+            .iter()
+            .for_each(|(cell, _)| mapping.clear_cell(cell));
+
+        // Sprite the wildcards stand in for, the first one's own sprite when
+        // group is only wildcards
+        let group_sprite = remnants
+            .iter()
+            .zip(specials.iter())
+            .find(|(_, special)| **special != Some(Special::Wildcard))
+            .map(|((_, sprite), _)| *sprite)
+            .or(remnants.first().map(|(_, sprite)| *sprite))
+            .unwrap_or(NO_SPRITE);
+
+        // Wildcards first, so sprites are back to complete groups before
+        // bombs look for partners of what they blast
+        let wildcards = specials
+            .iter()
+            .zip(remnants.iter())
+            .filter(|(special, _)| **special == Some(Special::Wildcard))
+            .map(|(_, (cell, sprite))| {
+                let rebalanced = if *sprite == group_sprite || rule.matches(*sprite, group_sprite) {
+                    Vec::new()
+                } else {
+                    Self::orphans(mapping, cell, *sprite, group_size, rule)
+                };
+                rebalanced.iter().for_each(|partner| {
+                    mapping.set_sprite(partner, group_sprite).unwrap();
+                });
+                Effect::Wildcard {
+                    cell: *cell,
+                    sprite: group_sprite,
+                    rebalanced,
+                }
+            })
+            .collect::<Vec<_>>();
+        let bombs = specials
+            .iter()
+            .zip(remnants.iter())
+            .filter(|(special, _)| **special == Some(Special::Bomb))
+            .map(|(_, (cell, _))| Effect::Explode {
+                origin: *cell,
+                cleared: Self::explode(mapping, cell, group_size, rule),
+            })
+            .collect::<Vec<_>>();
+        wildcards.into_iter().chain(bombs).collect()
+    }
+
+    /// Tiles left matching `sprite` under `rule` beyond complete groups,
+    /// closest to `origin` first
+    fn orphans(
+        mapping: &Mapping,
+        origin: &Cell,
+        sprite: SpriteId,
+        group_size: usize,
+        rule: &dyn MatchRule,
+    ) -> Vec<Cell> {
+        let mut partners: Vec<Cell> = mapping
+            .mutable_cells()
+            .filter(|cell| {
+                let other = mapping.get_sprite(cell);
+                other != NO_SPRITE && (other == sprite || rule.matches(sprite, other))
+            })
+            .collect();
+        let distance =
+            |cell: &Cell| cell.column.abs_diff(origin.column) + cell.row.abs_diff(origin.row);
+        partners.sort_by_key(|cell| (distance(cell), *cell));
+        partners.truncate(partners.len() % group_size);
+        partners
+    }
+
+    /// Clear occupied neighbours of `origin`, then the tiles closest to
+    /// `origin` they would have been matched with, so no sprite is left
+    /// without a complete group
+    fn explode(
+        mapping: &mut Mapping,
+        origin: &Cell,
+        group_size: usize,
+        rule: &dyn MatchRule,
+    ) -> Vec<(Cell, SpriteId)> {
+        let mut cleared: Vec<(Cell, SpriteId)> = origin
+            .neighbours()
+            .into_values()
+            .filter(|cell| mapping.check_valid_cell(cell))
+            .map(|cell| (cell, mapping.get_sprite(&cell)))
+            .filter(|&(_, sprite)| sprite != NO_SPRITE)
+            .collect();
+        cleared.sort();
+        cleared
+            .iter()
+            .for_each(|(cell, _)| mapping.clear_cell(cell));
+
+        // One sprite for each set of sprites matching each other
+        let mut sprites: Vec<SpriteId> = Vec::new();
+        for (_, sprite) in cleared.iter() {
+            if !sprites
+                .iter()
+                .any(|other| other == sprite || rule.matches(*other, *sprite))
+            {
+                sprites.push(*sprite);
+            }
+        }
+        for sprite in sprites {
+            for cell in Self::orphans(mapping, origin, sprite, group_size, rule) {
+                cleared.push((cell, mapping.get_sprite(&cell)));
+                mapping.clear_cell(&cell);
+            }
+        }
+        cleared
+    }
+
+    /// Let chameleons whose period is due swap sprite with the next regular
+    /// tile holding a different sprite, so every sprite still comes in complete
+    /// groups
+    fn camouflage(&mut self, mapping: &mut Mapping) {
+        let mut chameleons: Vec<Cell> = mapping
+            .specials()
+            .filter(|(_, special)| match special {
+                Special::Chameleon { period } => *period > 0 && self.moves.is_multiple_of(*period),
+                _ => false,
+            })
+            .map(|(cell, _)| *cell)
+            .collect();
+        chameleons.sort();
+
+        let cells: Vec<Cell> = mapping.mutable_cells().collect();
+        for cell in chameleons {
+            let from = mapping.get_sprite(&cell);
+            let Some(position) = cells.iter().position(|other| *other == cell) else {
+                continue;
+            };
+            let partner = cells
+                .iter()
+                .cycle()
+                .skip(position + 1)
+                .take(cells.len() - 1)
+                .find(|other| {
+                    let sprite = mapping.get_sprite(other);
+                    sprite != NO_SPRITE && sprite != from && mapping.get_special(other).is_none()
+                });
+            if let Some(&partner) = partner {
+                let to = mapping.get_sprite(&partner);
+                mapping.swap_sprites(&cell, &partner);
+                self.emit(Event::Effect(Effect::Camouflage {
+                    cell,
+                    partner,
+                    from,
+                    to,
+                }));
+            }
+        }
+    }
+
+    /// Groups of cells which can be matched now, each consecutive pair in a
+    /// group being connectable
+    pub fn available_moves(&self, mapping: &Mapping) -> Vec<Vec<Cell>> {
+        let pairs = mapping.available_moves(self.rule.as_ref());
+        if self.group_size == 2 {
+            return pairs
+                .into_iter()
+                .map(|(first, second)| vec![first, second])
+                .collect();
+        }
+
+        let mut graph: HashMap<Cell, Vec<Cell>> = HashMap::new();
+        for (first, second) in pairs {
+            graph.entry(first).or_default().push(second);
+            graph.entry(second).or_default().push(first);
+        }
+        let mut starts: Vec<Cell> = graph.keys().copied().collect();
+        starts.sort();
+
+        let mut groups = Vec::new();
+        for start in starts {
+            self.extend_group(mapping, &graph, &mut vec![start], &mut groups);
+        }
+        groups
+    }
+
+    /// Depth-first search for groups containing `chain`
+    fn extend_group(
+        &self,
+        mapping: &Mapping,
+        graph: &HashMap<Cell, Vec<Cell>>,
+        chain: &mut Vec<Cell>,
+        groups: &mut Vec<Vec<Cell>>,
+    ) {
+        if chain.len() == self.group_size {
+            groups.push(chain.clone());
+            return;
+        }
+        let is_regular = |cell: &Cell| mapping.get_special(cell) != Some(Special::Wildcard);
+        let last = *chain.last().unwrap();
+        for next in graph.get(&last).into_iter().flatten() {
+            let is_matching = !chain.contains(next)
+                && (!is_regular(next)
+                    || chain.iter().filter(|cell| is_regular(cell)).all(|cell| {
+                        self.rule
+                            .matches(mapping.get_sprite(cell), mapping.get_sprite(next))
+                    }));
+            if is_matching {
+                chain.push(*next);
+                self.extend_group(mapping, graph, chain, groups);
+                chain.pop();
+            }
+        }
+    }
+
+    /// Take all events happened since last call
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Move selection and destroying couples along with transformed board.
+    /// Must be called before `mapping.transform`, while cells still refer to
+    /// old positions, see `GameSession::transform`.
+    pub(crate) fn transform(&mut self, mapping: &Mapping, transform: Transform) {
+        let remap = |cell: &mut Cell| *cell = mapping.transform_cell(transform, cell);
+        self.select_buf.for_each_mut(remap);
+        self.destroy_buf.for_each_mut(|couple| {
+            couple.remnants.iter_mut().for_each(|(cell, _)| remap(cell));
+            couple.nodes.iter_mut().for_each(remap);
+        });
+    }
+
     pub fn get_selection(&self) -> Option<&Cell> {
         self.select_buf.latest()
     }
 
+    /// All selected cells in order of selection
+    pub fn get_selections(&self) -> Vec<&Cell> {
+        self.select_buf
+            .poll()
+            .iter()
+            .filter_map(|o| o.as_ref())
+            .collect()
+    }
+
     pub fn poll_destroying(&self) -> Vec<&Couple> {
         self.destroy_buf
             .poll()
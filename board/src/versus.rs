@@ -0,0 +1,135 @@
+use crate::prelude::*;
+
+/// Players racing on their own copy of the same board. First to clear wins,
+/// otherwise highest score when time is up or nobody can play anymore.
+pub struct Versus {
+    sessions: Vec<GameSession>,
+    /// Countdown of whole race
+    time_left: f64,
+    pub time_limit: f64,
+    winners: Option<Vec<PlayerId>>,
+}
+
+impl Versus {
+    pub fn new(
+        rules: Rules,
+        seed: u64,
+        players: usize,
+        time_limit: f64,
+    ) -> Result<Self, &'static str> {
+        if players < 2 {
+            return Err("versus needs at least 2 players");
+        }
+        if time_limit.is_nan() || time_limit <= 0. {
+            return Err("time limit must be positive");
+        }
+        let sessions = (0..players)
+            .map(|player| {
+                let mut session = GameSession::new(rules.clone(), seed);
+                session.update(&[], 0.);
+                session.set_player(player);
+                session
+            })
+            .collect();
+        Ok(Self {
+            sessions,
+            time_left: time_limit,
+            time_limit,
+            winners: None,
+        })
+    }
+
+    pub fn sessions(&self) -> &[GameSession] {
+        &self.sessions
+    }
+
+    pub fn time_left(&self) -> f64 {
+        self.time_left
+    }
+
+    /// Checksum of each session, in player order
+    pub fn checksums(&self) -> Vec<u64> {
+        self.sessions.iter().map(GameSession::checksum).collect()
+    }
+
+    /// Replace session of `player` with an authoritative copy after a desync
+    pub fn resync(
+        &mut self,
+        player: PlayerId,
+        session: GameSession,
+        time_left: f64,
+    ) -> Result<(), &'static str> {
+        let slot = self.sessions.get_mut(player).ok_or("no such player")?;
+        *slot = session;
+        self.time_left = time_left;
+        Ok(())
+    }
+
+    /// Winning players once race is over, several on a draw
+    pub fn winners(&self) -> Option<&[PlayerId]> {
+        self.winners.as_deref()
+    }
+
+    fn leaders(&self) -> Vec<PlayerId> {
+        let best = self
+            .sessions
+            .iter()
+            .map(|session| session.scoring().total())
+            .max();
+        (0..self.sessions.len())
+            .filter(|idx| Some(self.sessions[*idx].scoring().total()) == best)
+            .collect()
+    }
+
+    /// Apply input of each player, in player order, return events of each
+    /// session. Pause from any player pauses everyone.
+    pub fn update(&mut self, inputs: &[Vec<Action>], dt: f64) -> Vec<Vec<Event>> {
+        if self.winners.is_some() {
+            return vec![Vec::new(); self.sessions.len()];
+        }
+        let pause = inputs
+            .iter()
+            .flatten()
+            .any(|action| *action == Action::Pause);
+        let events: Vec<Vec<Event>> = self
+            .sessions
+            .iter_mut()
+            .enumerate()
+            .map(|(player, session)| {
+                let mut input: Vec<Action> = inputs
+                    .get(player)
+                    .into_iter()
+                    .flatten()
+                    .filter(|action| **action != Action::Pause)
+                    .copied()
+                    .collect();
+                if pause {
+                    input.push(Action::Pause);
+                }
+                session.update(&input, dt)
+            })
+            .collect();
+
+        let paused = self
+            .sessions
+            .iter()
+            .any(|session| session.state() == State::Paused);
+        if !paused {
+            self.time_left = (self.time_left - dt).max(0.);
+        }
+        let cleared: Vec<PlayerId> = (0..self.sessions.len())
+            .filter(|idx| self.sessions[*idx].state() == State::Won)
+            .collect();
+        if !cleared.is_empty() {
+            self.winners = Some(cleared);
+        } else if self.time_left == 0.
+            || self
+                .sessions
+                .iter()
+                .all(|session| session.state() == State::Lost)
+        {
+            self.winners = Some(self.leaders());
+        }
+        events
+    }
+}
@@ -0,0 +1,75 @@
+use crate::prelude::*;
+
+/// Per-cell face-up/face-down state for memory variant, laid out the same
+/// way as `Mapping`
+pub struct Visibility {
+    columns: usize,
+    rows: usize,
+    /// Instant until which the cell stays face-up, `None` when face-down
+    revealed_until: Vec<Option<f64>>,
+    /// How long a revealed cell stays face-up, in the same clock as
+    /// `Couple::epoch`
+    pub reveal_duration: f64,
+}
+
+impl Visibility {
+    /// All cells start face-down
+    pub fn new(mapping: &Mapping, reveal_duration: f64) -> Self {
+        Self {
+            columns: mapping.columns,
+            rows: mapping.rows,
+            revealed_until: vec![None; mapping.columns * mapping.rows],
+            reveal_duration,
+        }
+    }
+
+    fn cell2index(&self, cell: &Cell) -> usize {
+        cell.row * self.columns + cell.column
+    }
+
+    pub fn is_revealed(&self, cell: &Cell, instant: f64) -> bool {
+        matches!(self.revealed_until[self.cell2index(cell)], Some(until) if instant < until)
+    }
+
+    /// Turn cell face-up for `reveal_duration` starting at `instant`
+    pub fn reveal(&mut self, cell: &Cell, instant: f64) {
+        let idx = self.cell2index(cell);
+        self.revealed_until[idx] = Some(instant + self.reveal_duration);
+    }
+
+    /// Turn face-down all cells whose timer expired, return the hidden cells
+    pub fn update(&mut self, instant: f64) -> Vec<Cell> {
+        let columns = self.columns;
+        self.revealed_until
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(idx, until)| match until {
+                Some(t) if *t <= instant => {
+                    *until = None;
+                    Some(Cell {
+                        column: idx % columns,
+                        row: idx / columns,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Move timers along with transformed board
+    pub fn transform(&mut self, transform: Transform) {
+        let (columns, rows) = transform.dimensions(self.columns, self.rows);
+        let mut revealed_until = vec![None; columns * rows];
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let cell = Cell { column, row };
+                let target = transform.apply(&cell, self.columns, self.rows);
+                revealed_until[target.row * columns + target.column] =
+                    self.revealed_until[self.cell2index(&cell)];
+            }
+        }
+        self.columns = columns;
+        self.rows = rows;
+        self.revealed_until = revealed_until;
+    }
+}
@@ -0,0 +1,210 @@
+//! Headless versus server. Waits for players, then runs the race and
+//! broadcasts every tick with input of each player. Matches are hosted one
+//! after another.
+//!
+//! Races run in lockstep rather than by broadcasting board events: sessions
+//! are deterministic, so clients replaying the same seed and input end up
+//! with the same boards, and frames stay a few actions long. Server still
+//! runs every session, its checksums catch a client drifting away and a
+//! snapshot brings it back.
+
+use ::board::prelude::*;
+use std::{
+    io::ErrorKind,
+    net::{TcpListener, TcpStream},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tungstenite::{accept, Error, Message, WebSocket};
+
+type Socket = WebSocket<TcpStream>;
+
+/// Time a new connection has to complete handshake and join
+const JOIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Value following `flag` on command line
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|idx| args.get(idx + 1))
+        .cloned()
+}
+
+/// Print `message` and leave with failure status
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1)
+}
+
+/// Value following `flag` on command line parsed, `default` when absent
+fn parse_arg<T: FromStr>(flag: &str, default: T) -> T {
+    match arg_value(flag) {
+        Some(value) => value
+            .parse()
+            .unwrap_or_else(|_| exit_with(&format!("Invalid {}: {}", flag, value))),
+        None => default,
+    }
+}
+
+fn is_would_block(error: &Error) -> bool {
+    matches!(error, Error::Io(e) if e.kind() == ErrorKind::WouldBlock)
+}
+
+/// Pending data is flushed on next send
+fn send(client: &mut Socket, message: &ServerMessage) {
+    match client.send(Message::Text(message.to_string())) {
+        Err(e) if !is_would_block(&e) => eprintln!("Cannot send: {}", e),
+        _ => {}
+    }
+}
+
+fn broadcast(clients: &mut [Socket], message: &ServerMessage) {
+    clients.iter_mut().for_each(|client| send(client, message));
+}
+
+/// Accept connections until `players` have joined
+fn wait_players(listener: &TcpListener, players: usize) -> Vec<Socket> {
+    let mut clients = Vec::new();
+    while clients.len() < players {
+        let Ok((stream, address)) = listener.accept() else {
+            continue;
+        };
+        // Silent connection must not hold the match
+        let _ = stream.set_read_timeout(Some(JOIN_TIMEOUT));
+        let mut socket = match accept(stream) {
+            Ok(socket) => socket,
+            Err(e) => {
+                eprintln!("Handshake with {} failed: {}", address, e);
+                continue;
+            }
+        };
+        match socket.read() {
+            Ok(Message::Text(text)) => match text.parse() {
+                Ok(ClientMessage::Join { name }) => {
+                    println!("{} joined from {}", name, address);
+                    let _ = socket.get_mut().set_read_timeout(None);
+                    clients.push(socket);
+                }
+                _ => eprintln!("{} did not join", address),
+            },
+            _ => eprintln!("{} did not join", address),
+        }
+    }
+    clients
+}
+
+/// Boards each player asked to resync, by player
+type Resyncs = Vec<(PlayerId, PlayerId)>;
+
+/// Input received from each player since last tick with resync requests, or
+/// the player who left
+fn read_inputs(clients: &mut [Socket]) -> Result<(Vec<Vec<Action>>, Resyncs), PlayerId> {
+    let mut inputs = vec![Vec::new(); clients.len()];
+    let mut resyncs = Vec::new();
+    for (player, client) in clients.iter_mut().enumerate() {
+        loop {
+            match client.read() {
+                Ok(Message::Text(text)) => {
+                    match text.parse() {
+                        // Nobody pauses a race online
+                        Ok(ClientMessage::Input(actions)) => inputs[player]
+                            .extend(actions.into_iter().filter(|a| *a != Action::Pause)),
+                        Ok(ClientMessage::Resync(board)) => resyncs.push((player, board)),
+                        _ => {}
+                    }
+                }
+                Ok(Message::Close(_)) => return Err(player),
+                Ok(_) => {}
+                Err(e) if is_would_block(&e) => break,
+                Err(_) => return Err(player),
+            }
+        }
+    }
+    Ok((inputs, resyncs))
+}
+
+fn run_match(listener: &TcpListener, players: usize, time_limit: f64) {
+    let mut clients = wait_players(listener, players);
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or_default();
+    let rules = Rules::default();
+    let mut versus = Versus::new(rules.clone(), seed, players, time_limit).unwrap();
+    for (player, client) in clients.iter_mut().enumerate() {
+        let welcome = ServerMessage::Welcome {
+            player,
+            players,
+            seed,
+            time_limit,
+            rules: Box::new(rules.clone()),
+        };
+        if let Err(e) = client.send(Message::Text(welcome.to_string())) {
+            eprintln!("Cannot welcome player {}: {}", player, e);
+        }
+        let _ = client.get_mut().set_nonblocking(true);
+    }
+    println!("Race started with seed {}", seed);
+
+    let tick_duration = Duration::from_secs_f64(TICK);
+    let mut next_tick = Instant::now();
+    let mut tick = 0;
+    loop {
+        let (inputs, resyncs) = match read_inputs(&mut clients) {
+            Ok(inputs) => inputs,
+            Err(player) => {
+                println!("Player {} left", player);
+                broadcast(&mut clients, &ServerMessage::Left(player));
+                break;
+            }
+        };
+        versus.update(&inputs, TICK);
+        broadcast(&mut clients, &ServerMessage::Frame { dt: TICK, inputs });
+        tick += 1;
+        if tick % CHECK_TICKS == 0 {
+            let checksums = versus.checksums();
+            broadcast(&mut clients, &ServerMessage::Check { tick, checksums });
+        }
+        for (player, board) in resyncs {
+            let Some(session) = versus.sessions().get(board) else {
+                continue;
+            };
+            println!("Player {} resyncs board {} at tick {}", player, board, tick);
+            let snapshot = ServerMessage::Snapshot {
+                tick,
+                player: board,
+                time_left: versus.time_left(),
+                save: session.save(),
+            };
+            send(&mut clients[player], &snapshot);
+        }
+        if let Some(winners) = versus.winners() {
+            println!("Race over, winners: {:?}", winners);
+            break;
+        }
+        next_tick += tick_duration;
+        thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+    }
+    for client in clients.iter_mut() {
+        let _ = client.close(None);
+        let _ = client.flush();
+    }
+}
+
+fn main() {
+    let port = parse_arg("--port", DEFAULT_PORT);
+    let players = parse_arg("--players", 2);
+    let time_limit = parse_arg("--race-time", 180.);
+    // Same checks as every match will go through, fail now rather than then
+    if let Err(e) = Versus::new(Rules::default(), 0, players, time_limit) {
+        exit_with(&format!("Cannot host races: {}", e));
+    }
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .unwrap_or_else(|e| exit_with(&format!("Cannot bind port {}: {}", port, e)));
+    loop {
+        println!("Waiting for {} players on ws://127.0.0.1:{}", players, port);
+        run_match(&listener, players, time_limit);
+    }
+}
@@ -2,6 +2,9 @@ use ::board::prelude::*;
 use ::macroquad::prelude::*;
 use ::ui::prelude::*;
 
+mod net;
+mod storage;
+
 const TRANSPARENT: Color = Color {
     r: 255.,
     g: 255.,
@@ -9,6 +12,9 @@ const TRANSPARENT: Color = Color {
     a: 255.,
 };
 
+/// Connection color of each player
+const PLAYER_COLORS: [Color; HotSeat::MAX_PLAYERS] = [RED, SKYBLUE, GREEN, ORANGE];
+
 struct Drawer;
 
 impl Drawer {
@@ -24,19 +30,290 @@ impl Drawer {
         );
     }
 
-    fn draw_connector(board: &Region, trace: &[Cell]) {
+    fn draw_message(message: &str) {
+        let message_size = measure_text(message, None, 48, 1.);
+        draw_rectangle(
+            0.,
+            screen_height() / 2. - message_size.height * 2.,
+            screen_width(),
+            message_size.height * 4.,
+            Color::new(0., 0., 0., 0.75),
+        );
+        draw_text(
+            message,
+            (screen_width() - message_size.width) / 2.,
+            (screen_height() + message_size.height) / 2.,
+            48.,
+            WHITE,
+        );
+    }
+
+    fn draw_score(scoring: &Scoring) {
+        draw_text(&format!("Score: {}", scoring.total()), 10., 30., 32., WHITE);
+        if scoring.combo() > 0 {
+            draw_text(
+                &format!("Combo x{:.2}", scoring.multiplier()),
+                10.,
+                60.,
+                24.,
+                GOLD,
+            );
+        }
+    }
+
+    fn draw_power_ups(buttons: &[Region], inventory: &Inventory, armed: bool) {
+        for (idx, (button, power_up)) in buttons.iter().zip(PowerUp::ALL).enumerate() {
+            let count = inventory.count(power_up);
+            let x = button.coord.x + 2.;
+            let y = button.coord.y + 2.;
+            let w = button.size.width - 4.;
+            let h = button.size.height - 4.;
+            let color = match (power_up, armed, count) {
+                (PowerUp::Bomb, true, _) => ORANGE,
+                (_, _, 0) => DARKGRAY,
+                _ => DARKBLUE,
+            };
+            draw_rectangle(x, y, w, h, color);
+            draw_rectangle_lines(x, y, w, h, 2., LIGHTGRAY);
+            draw_text(
+                &format!("{} {}", idx + 1, power_up.name()),
+                x + 4.,
+                y + 16.,
+                16.,
+                WHITE,
+            );
+            draw_text(&format!("x{}", count), x + 4., y + h - 6., 20., WHITE);
+        }
+    }
+
+    /// Scores in player colors, current player marked with turn time left
+    fn draw_players(hot_seat: &HotSeat) {
+        for (idx, player) in hot_seat.players().iter().enumerate() {
+            let text = if idx == hot_seat.current() {
+                format!(
+                    "> {}: {} ({:.0}s)",
+                    player.name,
+                    player.score,
+                    hot_seat.turn_left()
+                )
+            } else {
+                format!("  {}: {}", player.name, player.score)
+            };
+            draw_text(
+                &text,
+                screen_width() - 260.,
+                30. + idx as f32 * 28.,
+                24.,
+                PLAYER_COLORS[idx],
+            );
+        }
+    }
+
+    fn draw_campaign_status(campaign: &Campaign) {
+        let status = format!(
+            "Level {}/{}: {}  Lives: {}  Total: {}",
+            (campaign.progress().level + 1).min(campaign.levels().len()),
+            campaign.levels().len(),
+            campaign.level().name,
+            campaign.progress().lives,
+            campaign.total_score()
+        );
+        draw_text(&status, 10., screen_height() - 16., 24., WHITE);
+    }
+
+    /// Lifetime numbers of profile with recent scores as bars, oldest left
+    fn draw_stats(profile: &str, lifetime: &LifetimeStats, current: &GameStats) {
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            screen_height(),
+            Color::new(0., 0., 0., 0.85),
+        );
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        let lines = [
+            format!("Stats of {}", profile),
+            format!(
+                "Games: {}  Wins: {}  Win rate: {}",
+                lifetime.games,
+                lifetime.wins,
+                or_dash(
+                    lifetime
+                        .win_rate()
+                        .map(|rate| format!("{:.0}%", rate * 100.))
+                )
+            ),
+            format!(
+                "Best score: {}  Average score: {}  Trend: {}",
+                lifetime.best_score,
+                or_dash(
+                    lifetime
+                        .average_score()
+                        .map(|score| format!("{:.0}", score))
+                ),
+                or_dash(lifetime.score_trend().map(|trend| format!("{:+.0}", trend)))
+            ),
+            format!(
+                "Average time: {}  Total time: {:.0}s",
+                or_dash(lifetime.average_time().map(|time| format!("{:.0}s", time))),
+                lifetime.total_time
+            ),
+            format!(
+                "Moves: {}  Accuracy: {}  Hints: {}  Shuffles: {}  Longest combo: {}",
+                lifetime.moves,
+                or_dash(
+                    lifetime
+                        .accuracy()
+                        .map(|rate| format!("{:.0}%", rate * 100.))
+                ),
+                lifetime.hints,
+                lifetime.shuffles,
+                lifetime.longest_combo
+            ),
+            format!(
+                "This game: {} moves, {} failed, {} hints, {} shuffles, combo {}, {} per match",
+                current.moves,
+                current.failed,
+                current.hints,
+                current.shuffles,
+                current.longest_combo,
+                or_dash(
+                    current
+                        .average_match_time()
+                        .map(|time| format!("{:.1}s", time))
+                )
+            ),
+        ];
+        for (idx, line) in lines.iter().enumerate() {
+            draw_text(line, 20., 40. + idx as f32 * 32., 28., WHITE);
+        }
+
+        // Recent scores
+        let top = 40. + lines.len() as f32 * 32.;
+        let height = (screen_height() - top - 40.).max(0.);
+        let max = lifetime
+            .recent
+            .iter()
+            .map(|game| game.score.max(0))
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+        let width = (screen_width() - 40.) / LifetimeStats::RECENT_GAMES as f32;
+        for (idx, game) in lifetime.recent.iter().enumerate() {
+            let bar = height * game.score.max(0) as f32 / max;
+            let color = if game.won { GREEN } else { RED };
+            draw_rectangle(
+                20. + idx as f32 * width + 2.,
+                top + height - bar,
+                width - 4.,
+                bar,
+                color,
+            );
+        }
+        draw_text(
+            "Press T to close",
+            20.,
+            screen_height() - 12.,
+            24.,
+            LIGHTGRAY,
+        );
+    }
+
+    /// Banner of unlocked achievement at top, fading out near the end of
+    /// `TOAST_DURATION`
+    fn draw_toast(achievement: &Achievement, age: f64) {
+        let alpha = ((TOAST_DURATION - age) / 0.5).clamp(0., 1.) as f32;
+        let title = format!("Achievement unlocked: {}", achievement.name);
+        let width = measure_text(&title, None, 28, 1.)
+            .width
+            .max(measure_text(&achievement.description, None, 22, 1.).width)
+            + 32.;
+        let x = (screen_width() - width) / 2.;
+        draw_rectangle(x, 12., width, 64., Color::new(0.1, 0.1, 0.1, 0.9 * alpha));
+        draw_rectangle_lines(x, 12., width, 64., 2., Color { a: alpha, ..GOLD });
+        draw_text(&title, x + 16., 40., 28., Color { a: alpha, ..GOLD });
+        draw_text(
+            &achievement.description,
+            x + 16.,
+            66.,
+            22.,
+            Color { a: alpha, ..WHITE },
+        );
+    }
+
+    /// High scores of `key` with entry of latest game highlighted
+    fn draw_leaderboard(key: &TableKey, table: Option<&Table>, highlight: Option<usize>) {
+        draw_rectangle(
+            0.,
+            0.,
+            screen_width(),
+            screen_height(),
+            Color::new(0., 0., 0., 0.85),
+        );
+        let title = format!(
+            "High scores: {} {}x{} {}",
+            key.mode, key.columns, key.rows, key.difficulty
+        );
+        draw_text(&title, 20., 40., 32., WHITE);
+        let entries = table.map_or(&[][..], |table| table.entries.as_slice());
+        if entries.is_empty() {
+            draw_text("No game finished yet", 20., 80., 28., LIGHTGRAY);
+        }
+        for (idx, entry) in entries.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<16} {:>8}  {:>4.0}s  {}",
+                idx + 1,
+                entry.name,
+                entry.score,
+                entry.time,
+                entry.date
+            );
+            let color = if highlight == Some(idx) { GOLD } else { WHITE };
+            draw_text(&line, 20., 80. + idx as f32 * 30., 28., color);
+        }
+        draw_text(
+            "1-9, 0: watch replay  E: export  I: import  L: close",
+            20.,
+            screen_height() - 12.,
+            24.,
+            LIGHTGRAY,
+        );
+    }
+
+    fn draw_timer_bar(time_left: f64, time_limit: f64, frozen: bool) {
+        let ratio = (time_left / time_limit).clamp(0., 1.) as f32;
+        let width = screen_width() * 0.8;
+        let x = screen_width() * 0.1;
+        let y = screen_height() * 0.05;
+        let color = match (frozen, ratio < 0.2) {
+            (true, _) => SKYBLUE,
+            (false, true) => RED,
+            (false, false) => GREEN,
+        };
+        draw_rectangle(x, y, width * ratio, 12., color);
+        draw_rectangle_lines(x, y, width, 12., 2., WHITE);
+        draw_text(
+            &format!("{:.0}s", time_left),
+            x + width + 8.,
+            y + 12.,
+            24.,
+            WHITE,
+        );
+    }
+
+    fn draw_connector(board: &Region, trace: &[Cell], color: Color) {
         for pair in trace.windows(2) {
             let first_center = board.cell_region(&pair[0]).unwrap().center();
             let second_center = board.cell_region(&pair[1]).unwrap().center();
-            draw_circle(first_center.x, first_center.y, 2.5, RED);
-            draw_circle(second_center.x, second_center.y, 2.5, RED);
+            draw_circle(first_center.x, first_center.y, 2.5, color);
+            draw_circle(second_center.x, second_center.y, 2.5, color);
             draw_line(
                 first_center.x,
                 first_center.y,
                 second_center.x,
                 second_center.y,
                 5.,
-                RED,
+                color,
             );
         }
     }
@@ -90,7 +367,7 @@ impl BoardDrawer {
             return;
         }
 
-        let region = board.cell_region(&cell).unwrap();
+        let region = board.cell_region(cell).unwrap();
 
         let sprite_x = region.coord.x + 2.;
         let sprite_y = region.coord.y + 2.;
@@ -126,8 +403,138 @@ impl BoardDrawer {
         );
     }
 
+    /// Sprite alone, e.g. next to text
+    fn draw_icon(&self, x: f32, y: f32, size: f32, sprite: SpriteId) {
+        draw_texture_ex(
+            &self.sprite_sheet,
+            x,
+            y,
+            TRANSPARENT,
+            DrawTextureParams {
+                dest_size: Some(vec2(size, size)),
+                source: Self::sprite2rect(sprite),
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Constraint as a line of text at baseline `y`, sprites shown as icons
+    fn draw_constraint(&self, constraint: &Constraint, x: f32, y: f32, color: Color) {
+        match constraint {
+            Constraint::MaxMoves(_) => {
+                draw_text(&constraint.to_string(), x, y, 24., color);
+            }
+            Constraint::ClearBefore { first, then } => {
+                let mut x = x;
+                for (text, sprite) in [("Clear every ", *first), (" before any ", *then)] {
+                    draw_text(text, x, y, 24., color);
+                    x += measure_text(text, None, 24, 1.).width;
+                    self.draw_icon(x, y - 22., 28., sprite);
+                    x += 28.;
+                }
+            }
+        }
+    }
+
+    /// Name, constraints and moves left of current puzzle at bottom, broken
+    /// constraint in red
+    fn draw_puzzle_status(&self, game: &PuzzleGame) {
+        let puzzle = game.puzzle();
+        let mut y = screen_height() - 16.;
+        let mut status = format!("Puzzle: {}", puzzle.name);
+        if let Some(moves_left) = game.moves_left() {
+            status.push_str(&format!("  Moves left: {}", moves_left));
+        }
+        status.push_str("  R: retry  B: puzzles");
+        draw_text(&status, 10., y, 24., WHITE);
+        for constraint in puzzle.constraints.iter().rev() {
+            y -= 32.;
+            let color = match game.state() {
+                PuzzleState::Broken(broken) if broken == *constraint => RED,
+                _ => LIGHTGRAY,
+            };
+            self.draw_constraint(constraint, 10., y, color);
+        }
+    }
+
+    /// List of puzzles with solved ones marked, and constraints of `selected`
+    fn draw_puzzle_browser(&self, puzzles: &[Puzzle], solved: &SolvedPuzzles, selected: usize) {
+        draw_text("Puzzles", 20., 40., 32., WHITE);
+        for (idx, puzzle) in puzzles.iter().enumerate() {
+            let mark = if solved.contains(&puzzle.id) {
+                "solved"
+            } else {
+                ""
+            };
+            let line = format!("{:>2}. {:<24} {}", idx + 1, puzzle.name, mark);
+            let color = if idx == selected { GOLD } else { WHITE };
+            draw_text(&line, 20., 80. + idx as f32 * 30., 28., color);
+        }
+        let mut y = 100. + puzzles.len() as f32 * 30.;
+        match puzzles.get(selected).map(|puzzle| &puzzle.constraints[..]) {
+            Some([]) => {
+                draw_text("Clear the board", 20., y, 24., LIGHTGRAY);
+            }
+            Some(constraints) => {
+                for constraint in constraints {
+                    self.draw_constraint(constraint, 20., y, LIGHTGRAY);
+                    y += 32.;
+                }
+            }
+            None => {}
+        }
+        draw_text(
+            "Up/Down: choose  Enter: play  Escape: quit",
+            20.,
+            screen_height() - 12.,
+            24.,
+            LIGHTGRAY,
+        );
+    }
+
+    fn draw_card_back(&self, board: &Region, cell: &Cell) {
+        let region = board.cell_region(cell).unwrap();
+
+        let x = region.coord.x + 2.;
+        let y = region.coord.y + 2.;
+        let width = region.size.width - 4.;
+        let height = region.size.height - 4.;
+
+        // Border
+        draw_rectangle(x, y, width, height, LIGHTGRAY);
+
+        // Back pattern
+        draw_rectangle(x + 4., y + 4., width - 8., height - 8., DARKBLUE);
+        draw_rectangle_lines(x + 8., y + 8., width - 16., height - 16., 2., SKYBLUE);
+    }
+
+    fn draw_special(&self, board: &Region, cell: &Cell, special: Special) {
+        let region = board.cell_region(cell).unwrap();
+
+        let x = region.coord.x;
+        let y = region.coord.y;
+        let w = region.size.width;
+        let h = region.size.height;
+
+        match special {
+            Special::Wildcard => {
+                draw_rectangle(x + 2., y + 2., w - 4., h - 4., GOLD);
+                let text_size = measure_text("*", None, h as u16, 1.);
+                draw_text(
+                    "*",
+                    x + (w - text_size.width) / 2.,
+                    y + (h + text_size.height) / 2.,
+                    h,
+                    BLACK,
+                );
+            }
+            Special::Bomb => draw_circle(x + w - 8., y + 8., 6., BLACK),
+            Special::Chameleon { .. } => draw_circle(x + w - 8., y + 8., 6., LIME),
+        }
+    }
+
     fn draw_select_border(&self, board: &Region, cell: &Cell) {
-        let region = board.cell_region(&cell).unwrap();
+        let region = board.cell_region(cell).unwrap();
 
         let x = region.coord.x;
         let y = region.coord.y;
@@ -137,37 +544,157 @@ impl BoardDrawer {
         draw_rectangle_lines(x, y, w, h, 8., RED);
     }
 
-    fn draw_board(&self, board: &Region, mapping: &Mapping) {
+    /// Keyboard cursor
+    fn draw_focus_ring(&self, board: &Region, cell: &Cell) {
+        let region = board.cell_region(cell).unwrap();
+        let x = region.coord.x;
+        let y = region.coord.y;
+        let w = region.size.width;
+        let h = region.size.height;
+        draw_rectangle_lines(x - 2., y - 2., w + 4., h + 4., 3., WHITE);
+        draw_rectangle_lines(x + 1., y + 1., w - 2., h - 2., 3., SKYBLUE);
+    }
+
+    /// Border of tile under pointer while dragging
+    fn draw_hover_border(&self, board: &Region, cell: &Cell, color: Color) {
+        let region = board.cell_region(cell).unwrap();
+        draw_rectangle_lines(
+            region.coord.x,
+            region.coord.y,
+            region.size.width,
+            region.size.height,
+            4.,
+            color,
+        );
+    }
+
+    /// Board with selection, latest matches, failed paths and hint
+    fn draw_session(&self, board: &Region, session: &GameSession) {
+        let connector = session.connector();
+        let now = session.clock();
+        // Draw current board after update
+        self.draw_board(board, session.mapping(), session.visibility(), now);
+
+        // Draw border for current selection
+        for cell in connector.get_selections() {
+            self.draw_select_border(board, cell)
+        }
+
+        // Draw pending destroy couples
+        for couple in connector
+            .poll_destroying()
+            .iter()
+            .filter(|couple| now - couple.epoch < 0.5)
+        {
+            for (cell, sprite_id) in couple.remnants.iter() {
+                self.draw_sprite(board, cell, *sprite_id);
+            }
+            for effect in couple.effects.iter() {
+                if let Effect::Explode { cleared, .. } = effect {
+                    for (cell, sprite_id) in cleared.iter() {
+                        self.draw_sprite(board, cell, *sprite_id);
+                    }
+                }
+            }
+            let color = PLAYER_COLORS[couple.player % PLAYER_COLORS.len()];
+            Drawer::draw_connector(board, &couple.nodes, color);
+        }
+
+        // Draw debug points
+        Drawer::draw_conquered(board, session.failed_paths());
+
+        // Draw hint
+        for cell in session.hint().into_iter().flatten() {
+            self.draw_hover_border(board, cell, SKYBLUE);
+        }
+    }
+
+    fn draw_board(
+        &self,
+        board: &Region,
+        mapping: &Mapping,
+        visibility: Option<&Visibility>,
+        instant: f64,
+    ) {
         for i in 0..mapping.columns {
             for j in 0..mapping.rows {
                 let cell = Cell { column: i, row: j };
-                self.draw_sprite(board, &cell, mapping.get_sprite(&cell));
+                let sprite = mapping.get_sprite(&cell);
+                match visibility {
+                    Some(visibility)
+                        if sprite != NO_SPRITE && !visibility.is_revealed(&cell, instant) =>
+                    {
+                        self.draw_card_back(board, &cell)
+                    }
+                    _ => {
+                        self.draw_sprite(board, &cell, sprite);
+                        if let Some(special) = mapping.get_special(&cell) {
+                            self.draw_special(board, &cell, special);
+                        }
+                    }
+                }
             }
         }
     }
 }
 
+/// Pointer gesture, in screen coordinate
+enum Gesture {
+    /// Pressed and released at about the same place
+    Click(Coordinate),
+    /// Still pressed after moving away from where it was pressed
+    Drag { from: Coordinate, to: Coordinate },
+    /// Released after dragging
+    Drop { from: Coordinate, to: Coordinate },
+}
+
 #[derive(Default)]
 struct Interaction {
     mouse_hold: Option<f64>,
+    /// Where mouse was pressed
+    press_coord: Option<Coordinate>,
 }
 
 impl Interaction {
-    fn get_click(&mut self) -> Option<(f32, f32)> {
-        match (self.mouse_hold, is_mouse_button_down(MouseButton::Left)) {
-            (None, true) => {
+    fn mouse_coord() -> Coordinate {
+        let (x, y) = mouse_position();
+        Coordinate { x, y }
+    }
+
+    fn get_gesture(&mut self) -> Option<Gesture> {
+        // Moving less than this is still a click
+        const DRAG_THRESHOLD: f32 = 4.;
+
+        let coord = Self::mouse_coord();
+        let moved = |from: &Coordinate| {
+            (from.x - coord.x).abs() > DRAG_THRESHOLD || (from.y - coord.y).abs() > DRAG_THRESHOLD
+        };
+        match (
+            self.mouse_hold,
+            self.press_coord,
+            is_mouse_button_down(MouseButton::Left),
+        ) {
+            (None, _, true) => {
                 self.mouse_hold = Some(get_time());
+                self.press_coord = Some(coord);
                 None
             }
-            (Some(instant), false) => {
-                let elapsed = get_time() - instant;
-                // Debounce
-                let should_trigger = elapsed > 0.01f64 && elapsed < 1.0f64;
+            (Some(_), Some(from), true) if moved(&from) => Some(Gesture::Drag { from, to: coord }),
+            (Some(instant), from, false) => {
                 self.mouse_hold = None;
-                if should_trigger {
-                    Some(mouse_position())
-                } else {
-                    None
+                self.press_coord = None;
+                match from {
+                    Some(from) if moved(&from) => Some(Gesture::Drop { from, to: coord }),
+                    _ => {
+                        let elapsed = get_time() - instant;
+                        // Debounce
+                        let should_trigger = elapsed > 0.01f64 && elapsed < 1.0f64;
+                        if should_trigger {
+                            Some(Gesture::Click(coord))
+                        } else {
+                            None
+                        }
+                    }
                 }
             }
             _ => None,
@@ -177,36 +704,810 @@ impl Interaction {
     fn check_exit(&self) -> bool {
         is_key_pressed(KeyCode::Escape)
     }
+
+    fn check_pause(&self) -> bool {
+        is_key_pressed(KeyCode::P)
+    }
+
+    fn check_shuffle(&self) -> bool {
+        is_key_pressed(KeyCode::R)
+    }
+
+    fn check_hint(&self) -> bool {
+        is_key_pressed(KeyCode::H)
+    }
+
+    fn check_undo(&self) -> bool {
+        is_key_pressed(KeyCode::U)
+    }
+
+    /// Number keys 1 to 5, in order of `PowerUp::ALL`
+    fn get_power_up_key(&self) -> Option<PowerUp> {
+        [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+        ]
+        .iter()
+        .zip(PowerUp::ALL)
+        .find(|(key, _)| is_key_pressed(**key))
+        .map(|(_, power_up)| power_up)
+    }
+
+    /// Arrow keys or WASD
+    fn get_cursor_move(&self) -> Option<Direction> {
+        [
+            (KeyCode::Up, Direction::Up),
+            (KeyCode::W, Direction::Up),
+            (KeyCode::Down, Direction::Down),
+            (KeyCode::S, Direction::Down),
+            (KeyCode::Left, Direction::Left),
+            (KeyCode::A, Direction::Left),
+            (KeyCode::Right, Direction::Right),
+            (KeyCode::D, Direction::Right),
+        ]
+        .iter()
+        .find(|(key, _)| is_key_pressed(*key))
+        .map(|(_, direction)| *direction)
+    }
+
+    fn check_cursor_select(&self) -> bool {
+        is_key_pressed(KeyCode::Space) || is_key_pressed(KeyCode::Enter)
+    }
+
+    fn check_confirm(&self) -> bool {
+        is_key_pressed(KeyCode::Enter)
+    }
+
+    /// Shuffle of mouse player in versus mode
+    fn check_right_click(&self) -> bool {
+        is_mouse_button_pressed(MouseButton::Right)
+    }
+
+    fn check_new_game(&self) -> bool {
+        is_key_pressed(KeyCode::N)
+    }
+
+    fn check_copy(&self) -> bool {
+        is_key_pressed(KeyCode::C)
+    }
+
+    /// Start puzzle over, shuffle isn't allowed there
+    fn check_retry(&self) -> bool {
+        is_key_pressed(KeyCode::R)
+    }
+
+    fn check_browse(&self) -> bool {
+        is_key_pressed(KeyCode::B)
+    }
+
+    fn check_stats(&self) -> bool {
+        is_key_pressed(KeyCode::T)
+    }
+
+    /// Number keys 1 to 3 while watching a replay
+    fn get_replay_speed(&self) -> Option<Speed> {
+        [
+            (KeyCode::Key1, Speed::Normal),
+            (KeyCode::Key2, Speed::Double),
+            (KeyCode::Key3, Speed::Instant),
+        ]
+        .iter()
+        .find(|(key, _)| is_key_pressed(*key))
+        .map(|(_, speed)| *speed)
+    }
+
+    fn check_leaderboard(&self) -> bool {
+        is_key_pressed(KeyCode::L)
+    }
+
+    fn check_export(&self) -> bool {
+        is_key_pressed(KeyCode::E)
+    }
+
+    fn check_import(&self) -> bool {
+        is_key_pressed(KeyCode::I)
+    }
+
+    /// Number keys 1 to 9 then 0 for entries of a high-score table
+    fn get_entry_key(&self) -> Option<usize> {
+        [
+            KeyCode::Key1,
+            KeyCode::Key2,
+            KeyCode::Key3,
+            KeyCode::Key4,
+            KeyCode::Key5,
+            KeyCode::Key6,
+            KeyCode::Key7,
+            KeyCode::Key8,
+            KeyCode::Key9,
+            KeyCode::Key0,
+        ]
+        .iter()
+        .position(|key| is_key_pressed(*key))
+    }
+
+    /// Seconds to jump in replay with left and right arrows
+    fn get_replay_seek(&self) -> Option<f64> {
+        if is_key_pressed(KeyCode::Left) {
+            Some(-5.)
+        } else if is_key_pressed(KeyCode::Right) {
+            Some(5.)
+        } else {
+            None
+        }
+    }
+}
+
+/// Build mode rules from command line flags on native build
+fn rules_from_args() -> Rules {
+    let has_flag = |flag: &str| std::env::args().any(|arg| arg == flag);
+    let mut rules = Rules::default();
+    // Triple-match variant
+    if has_flag("--triple") {
+        rules.group_size = 3;
+    }
+    // Sprites next to each other on sprite sheet form a family
+    if has_flag("--families") {
+        rules.matching = Matching::Families { size: 2 };
+    }
+    // Special tiles
+    if has_flag("--specials") {
+        rules.specials = vec![
+            Special::Wildcard,
+            Special::Wildcard,
+            Special::Bomb,
+            Special::Bomb,
+            Special::Chameleon { period: 3 },
+        ];
+    }
+    // Face-down memory variant
+    if has_flag("--face-down") {
+        rules.face_down = Some(1.5);
+    }
+    // One of each power-up, more earned along the way
+    if has_flag("--power-ups") {
+        rules.power_ups = PowerUp::ALL.iter().map(|power_up| (*power_up, 1)).collect();
+        rules.earn_every = Some(5);
+    }
+    // Countdown with time bonus on match
+    if has_flag("--time-attack") {
+        rules.time_limit = Some(120.);
+        rules.time_bonus = 3.;
+        rules.shuffle_cost = 10.;
+    }
+    rules
+}
+
+/// Actions for dropping a tile dragged from `from` on `to`. The dragged tile
+/// replaces an unrelated unfinished selection instead of joining it.
+fn drop_actions(selection: &[&Cell], from: Cell, to: Cell) -> Vec<Action> {
+    match selection.last() {
+        Some(&&last) if last == from => vec![Action::Select(to)],
+        Some(_) => vec![Action::AlterSelection(from), Action::Select(to)],
+        None => vec![Action::Select(from), Action::Select(to)],
+    }
+}
+
+/// Key of saved campaign progress
+const PROGRESS_KEY: &str = "campaign_progress";
+
+/// Key of free game in progress, offered on next launch
+const SAVE_KEY: &str = "saved_game";
+
+const CONTINUE_MESSAGE: &str = "Enter: continue saved game, N: new game";
+
+/// Key of the latest finished game, played again with `--replay`
+const REPLAY_KEY: &str = "last_replay";
+
+/// Value following `flag` on command line
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|idx| args.get(idx + 1))
+        .cloned()
+}
+
+/// Player profile from `--profile <name>` on native build
+fn profile_from_args() -> String {
+    arg_value("--profile")
+        .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Computer player from `--bot <easy|normal|hard>`, taking the last seat of
+/// hot-seat or the right board of versus
+fn bot_from_args(seed: u64) -> Option<Bot> {
+    let difficulty = arg_value("--bot")?.parse().ok()?;
+    // Own generator, so bot doesn't play the same as board was generated
+    Some(Bot::new(difficulty, seed.rotate_left(32)))
+}
+
+/// Hot-seat game from `--players <2-4>` and `--turn-time <seconds>`
+fn hot_seat_from_args(seed: u64, bot: Option<&Bot>) -> Option<HotSeat> {
+    let players: usize = arg_value("--players")?.parse().ok()?;
+    let turn_time = arg_value("--turn-time")
+        .and_then(|time| time.parse().ok())
+        .unwrap_or(15.);
+    let mut names: Vec<String> = (1..=players).map(|idx| format!("Player {}", idx)).collect();
+    if let (Some(bot), Some(name)) = (bot, names.last_mut()) {
+        *name = format!("Bot ({})", bot.difficulty);
+    }
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    HotSeat::new(rules_from_args(), seed, &names, turn_time).ok()
+}
+
+/// Split-screen race from `--versus` and `--race-time <seconds>`
+fn versus_from_args(seed: u64) -> Option<Versus> {
+    if !std::env::args().any(|arg| arg == "--versus") {
+        return None;
+    }
+    let time_limit = arg_value("--race-time")
+        .and_then(|time| time.parse().ok())
+        .unwrap_or(180.);
+    Versus::new(rules_from_args(), seed, 2, time_limit).ok()
+}
+
+/// Race of mouse player on left board against keyboard player or bot on right
+/// board
+async fn play_versus(mut versus: Versus, mut bot: Option<Bot>, board_drawer: &BoardDrawer) {
+    let mut interaction: Interaction = Default::default();
+    let mut cursor: Option<Cursor> = None;
+    loop {
+        if interaction.check_exit() || is_quit_requested() {
+            break;
+        }
+        let screen = Size {
+            width: screen_width(),
+            height: screen_height(),
+        };
+        // Boards may differ in size after rotation
+        let boards: Vec<Region> = versus
+            .sessions()
+            .iter()
+            .enumerate()
+            .map(|(idx, session)| {
+                let mapping = session.mapping();
+                screen.game_board_regions(2, mapping.columns, mapping.rows)[idx]
+            })
+            .collect();
+
+        //----------------------------------------------Mouse player on left
+        let mut mouse_input = Vec::new();
+        let mouse_session = &versus.sessions()[0];
+        match interaction.get_gesture() {
+            Some(Gesture::Click(coord)) => {
+                if let Ok(cell) = boards[0].cell_from_coord(&coord) {
+                    mouse_input.push(Action::Select(cell));
+                }
+            }
+            Some(Gesture::Drop { from, to }) => {
+                match (
+                    boards[0].cell_from_coord(&from),
+                    boards[0].cell_from_coord(&to),
+                ) {
+                    (Ok(from), Ok(to)) if from != to => {
+                        let selection = mouse_session.connector().get_selections();
+                        mouse_input.extend(drop_actions(&selection, from, to));
+                    }
+                    (Ok(from), Ok(_)) => mouse_input.push(Action::Select(from)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        if interaction.check_right_click() {
+            mouse_input.push(Action::Shuffle);
+        }
+
+        //-------------------------------------------Keyboard player on right
+        let mut keyboard_input = Vec::new();
+        let mapping = versus.sessions()[1].mapping();
+        let cursor = cursor.get_or_insert_with(|| Cursor::new(mapping, true));
+        cursor.clamp(mapping);
+        if let Some(direction) = interaction.get_cursor_move() {
+            cursor.step(mapping, direction);
+        }
+        if interaction.check_cursor_select() {
+            keyboard_input.push(Action::Select(cursor.cell));
+        }
+        if interaction.check_shuffle() {
+            keyboard_input.push(Action::Shuffle);
+        }
+        if interaction.check_hint() {
+            keyboard_input.push(Action::Hint);
+        }
+        if interaction.check_pause() {
+            keyboard_input.push(Action::Pause);
+        }
+        if let Some(bot) = bot.as_mut() {
+            // Pause still works from keyboard
+            keyboard_input.retain(|action| *action == Action::Pause);
+            keyboard_input.extend(bot.update(&versus.sessions()[1], get_frame_time() as f64));
+        }
+
+        versus.update(&[mouse_input, keyboard_input], get_frame_time() as f64);
+
+        //---------------------------------------------------------------Render
+        clear_background(BLANK);
+        if versus.sessions()[0].state() == State::Paused {
+            Drawer::draw_message("Paused");
+            next_frame().await;
+            continue;
+        }
+        for (idx, (board, session)) in boards.iter().zip(versus.sessions()).enumerate() {
+            board_drawer.draw_session(board, session);
+            let name = match (idx, &bot) {
+                (1, Some(bot)) => format!("Bot ({})", bot.difficulty),
+                _ => format!("Player {}", idx + 1),
+            };
+            draw_text(
+                &format!("{}: {}", name, session.scoring().total()),
+                board.coord.x,
+                board.coord.y - 8.,
+                28.,
+                PLAYER_COLORS[idx],
+            );
+            if session.state() == State::Stuck {
+                draw_text(
+                    "No more moves, shuffle!",
+                    board.coord.x,
+                    board.coord.y + board.size.height + 24.,
+                    24.,
+                    WHITE,
+                );
+            }
+        }
+        if bot.is_none() {
+            board_drawer.draw_focus_ring(&boards[1], &cursor.cell);
+        }
+        Drawer::draw_timer_bar(versus.time_left(), versus.time_limit, false);
+        match versus.winners() {
+            Some([winner]) => Drawer::draw_message(&format!("Player {} wins!", winner + 1)),
+            Some(_) => Drawer::draw_message("Draw!"),
+            None => {}
+        }
+        next_frame().await;
+    }
+}
+
+/// Race against other players through versus server, own board is played
+/// with mouse or keyboard and every board advances on server ticks only
+async fn play_online(url: &str, name: &str, board_drawer: &BoardDrawer) {
+    let mut connection = match net::Connection::connect(url) {
+        Ok(connection) => connection,
+        Err(e) => {
+            error!("Cannot connect to {}: {}", url, e);
+            return;
+        }
+    };
+    connection.send(&ClientMessage::Join { name: name.into() }.to_string());
+
+    let mut interaction: Interaction = Default::default();
+    let mut cursor: Option<Cursor> = None;
+    let mut keyboard_focus = false;
+    let mut online: Option<(Versus, PlayerId)> = None;
+    let mut left: Option<PlayerId> = None;
+    // Frames applied, and frames at latest matching checksums
+    let mut tick = 0;
+    let mut last_check = 0;
+    loop {
+        if interaction.check_exit() || is_quit_requested() {
+            break;
+        }
+        while let Some(text) = connection.receive() {
+            match text.parse() {
+                Ok(ServerMessage::Welcome {
+                    player,
+                    players,
+                    seed,
+                    time_limit,
+                    rules,
+                }) => {
+                    online = Versus::new(*rules, seed, players, time_limit)
+                        .ok()
+                        .map(|versus| (versus, player));
+                }
+                Ok(ServerMessage::Frame { dt, inputs }) => {
+                    if let Some((versus, _)) = online.as_mut() {
+                        versus.update(&inputs, dt);
+                        tick += 1;
+                    }
+                }
+                Ok(ServerMessage::Check {
+                    tick: server_tick,
+                    checksums,
+                }) => {
+                    let Some((versus, _)) = online.as_ref() else {
+                        continue;
+                    };
+                    if tick != server_tick {
+                        warn!("At tick {} but server checked tick {}", tick, server_tick);
+                    }
+                    for (board, (own, server)) in
+                        versus.checksums().iter().zip(checksums).enumerate()
+                    {
+                        if *own != server {
+                            warn!(
+                                "Board {} desynced between tick {} and {}",
+                                board + 1,
+                                last_check,
+                                server_tick
+                            );
+                            connection.send(&ClientMessage::Resync(board).to_string());
+                        }
+                    }
+                    last_check = server_tick;
+                }
+                Ok(ServerMessage::Snapshot {
+                    tick: server_tick,
+                    player,
+                    time_left,
+                    save,
+                }) => {
+                    let Some((versus, _)) = online.as_mut() else {
+                        continue;
+                    };
+                    match GameSession::load_snapshot(&save)
+                        .and_then(|session| versus.resync(player, session, time_left))
+                    {
+                        Ok(()) => info!("Board {} resynced at tick {}", player + 1, server_tick),
+                        Err(e) => warn!("Cannot resync board {}: {}", player + 1, e),
+                    }
+                    tick = server_tick;
+                }
+                Ok(ServerMessage::Left(player)) => left = Some(player),
+                Err(e) => warn!("Invalid server message: {}", e),
+            }
+        }
+
+        clear_background(BLANK);
+        let Some((versus, player)) = online.as_ref() else {
+            Drawer::draw_message(match connection.is_closed() {
+                true => "Connection lost",
+                false => "Waiting for players...",
+            });
+            next_frame().await;
+            continue;
+        };
+        let player = *player;
+        let screen = Size {
+            width: screen_width(),
+            height: screen_height(),
+        };
+        let players = versus.sessions().len();
+        let boards: Vec<Region> = versus
+            .sessions()
+            .iter()
+            .enumerate()
+            .map(|(idx, session)| {
+                let mapping = session.mapping();
+                screen.game_board_regions(players, mapping.columns, mapping.rows)[idx]
+            })
+            .collect();
+
+        //-------------------------------------------------Input of own board
+        let mut input = Vec::new();
+        let own = &versus.sessions()[player];
+        let mapping = own.mapping();
+        let cursor = cursor.get_or_insert_with(|| Cursor::new(mapping, true));
+        cursor.clamp(mapping);
+        match interaction.get_gesture() {
+            Some(Gesture::Click(coord)) => {
+                keyboard_focus = false;
+                if let Ok(cell) = boards[player].cell_from_coord(&coord) {
+                    input.push(Action::Select(cell));
+                }
+            }
+            Some(Gesture::Drop { from, to }) => {
+                keyboard_focus = false;
+                match (
+                    boards[player].cell_from_coord(&from),
+                    boards[player].cell_from_coord(&to),
+                ) {
+                    (Ok(from), Ok(to)) if from != to => {
+                        let selection = own.connector().get_selections();
+                        input.extend(drop_actions(&selection, from, to));
+                    }
+                    (Ok(from), Ok(_)) => input.push(Action::Select(from)),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        if let Some(direction) = interaction.get_cursor_move() {
+            keyboard_focus = true;
+            cursor.step(mapping, direction);
+        }
+        if interaction.check_cursor_select() {
+            keyboard_focus = true;
+            input.push(Action::Select(cursor.cell));
+        }
+        if interaction.check_shuffle() || interaction.check_right_click() {
+            input.push(Action::Shuffle);
+        }
+        if interaction.check_hint() {
+            input.push(Action::Hint);
+        }
+        if !input.is_empty() && versus.winners().is_none() && left.is_none() {
+            connection.send(&ClientMessage::Input(input).to_string());
+        }
+
+        //---------------------------------------------------------------Render
+        for (idx, (board, session)) in boards.iter().zip(versus.sessions()).enumerate() {
+            board_drawer.draw_session(board, session);
+            let label = match idx == player {
+                true => format!("You: {}", session.scoring().total()),
+                false => format!("Player {}: {}", idx + 1, session.scoring().total()),
+            };
+            draw_text(
+                &label,
+                board.coord.x,
+                board.coord.y - 8.,
+                28.,
+                PLAYER_COLORS[idx % PLAYER_COLORS.len()],
+            );
+            if idx == player && session.state() == State::Stuck {
+                draw_text(
+                    "No more moves, shuffle!",
+                    board.coord.x,
+                    board.coord.y + board.size.height + 24.,
+                    24.,
+                    WHITE,
+                );
+            }
+        }
+        if keyboard_focus {
+            board_drawer.draw_focus_ring(&boards[player], &cursor.cell);
+        }
+        Drawer::draw_timer_bar(versus.time_left(), versus.time_limit, false);
+        match (versus.winners(), left) {
+            (Some(winners), _) if winners == [player] => Drawer::draw_message("You win!"),
+            (Some([winner]), _) => Drawer::draw_message(&format!("Player {} wins!", winner + 1)),
+            (Some(_), _) => Drawer::draw_message("Draw!"),
+            (None, Some(gone)) => Drawer::draw_message(&format!("Player {} left", gone + 1)),
+            (None, None) if connection.is_closed() => Drawer::draw_message("Connection lost"),
+            _ => {}
+        }
+        next_frame().await;
+    }
+}
+
+/// Key of lifetime stats of profile
+fn stats_key(profile: &str) -> String {
+    format!("stats_{}", profile)
+}
+
+/// Key of unlocked achievements of profile
+fn achievements_key(profile: &str) -> String {
+    format!("achievements_{}", profile)
+}
+
+/// Key of high-score tables of profile
+fn leaderboards_key(profile: &str) -> String {
+    format!("leaderboards_{}", profile)
+}
+
+/// File high-score tables are exported to and imported from
+const SCORES_FILE: &str = "meme_connect_scores.txt";
+
+/// Seconds each achievement toast stays on screen
+const TOAST_DURATION: f64 = 3.;
+
+/// Key of daily challenge attempts of profile
+fn daily_key(profile: &str) -> String {
+    format!("daily_{}", profile)
+}
+
+/// Key of solved puzzles of profile
+fn puzzles_key(profile: &str) -> String {
+    format!("puzzles_{}", profile)
+}
+
+/// What the binary is running
+enum Game {
+    /// Single board with rules from command line
+    Free(GameSession),
+    Campaign(Campaign),
+    /// Latest finished game played again
+    Replay(Box<Playback>),
+    HotSeat(HotSeat),
+    /// Board of the day, only first attempt is ranked
+    Daily {
+        session: GameSession,
+        date: Date,
+        ranked: bool,
+    },
+    /// Hand-designed board picked from puzzle browser
+    Puzzle(PuzzleGame),
+}
+
+impl Game {
+    /// Mode of high-score table, if game goes on one
+    fn mode(&self) -> Option<&'static str> {
+        match self {
+            Self::Free(_) => Some("free"),
+            Self::Campaign(_) => Some("campaign"),
+            // Practice runs of the day don't rank
+            Self::Daily { ranked, .. } => ranked.then_some("daily"),
+            Self::Replay(_) | Self::HotSeat(_) | Self::Puzzle(_) => None,
+        }
+    }
+
+    /// Whole game is over, not just a level of campaign
+    fn is_over(&self) -> bool {
+        match self {
+            Self::Campaign(campaign) => {
+                matches!(campaign.stage(), Stage::GameOver | Stage::Finished)
+            }
+            game => game.session().state().is_over(),
+        }
+    }
+
+    fn session(&self) -> &GameSession {
+        match self {
+            Self::Free(session) => session,
+            Self::Campaign(campaign) => campaign.session(),
+            Self::Replay(playback) => playback.session(),
+            Self::HotSeat(hot_seat) => hot_seat.session(),
+            Self::Daily { session, .. } => session,
+            Self::Puzzle(puzzle) => puzzle.session(),
+        }
+    }
+
+    fn update(&mut self, input: &[Action], dt: f64) -> Vec<Event> {
+        match self {
+            Self::Free(session) => session.update(input, dt),
+            Self::Campaign(campaign) => {
+                let stage = campaign.stage();
+                let events = campaign.update(input, dt);
+                if campaign.stage() != stage {
+                    match campaign.stage() {
+                        // Next launch starts a new campaign
+                        Stage::GameOver | Stage::Finished => storage::remove(PROGRESS_KEY),
+                        _ => storage::save(PROGRESS_KEY, &campaign.progress().to_string()),
+                    }
+                }
+                events
+            }
+            // Player input only controls playback
+            Self::Replay(playback) => {
+                let desynced = playback.desync().is_some();
+                let events = playback.update(dt);
+                if let (false, Some(steps)) = (desynced, playback.desync()) {
+                    warn!(
+                        "Replay desynced between step {} and {}",
+                        steps.start, steps.end
+                    );
+                }
+                events
+            }
+            Self::HotSeat(hot_seat) => hot_seat.update(input, dt),
+            Self::Daily { session, .. } => session.update(input, dt),
+            Self::Puzzle(puzzle) => puzzle.update(input, dt),
+        }
+    }
 }
 
 #[macroquad::main("meme-connect")]
 pub async fn main() {
     Drawer::draw_loading_screen();
+    // Window close is handled in loop to save game first
+    prevent_quit();
     // Seed random
-    rand::srand((get_time() * 1000.) as u64);
-    let mut mapping = Mapping::new(16, 16);
-    let playground = mapping.mutable_cells();
-    let mut fillable_cells: Vec<Cell> = playground
-        .filter(|cell| mapping.check_fillable_cell(cell).is_ok())
-        .collect();
-    let mut shuffled = Vec::new();
-    for _ in 0..64 {
-        if fillable_cells.is_empty() {
-            break;
+    let seed = (get_time() * 1000.) as u64;
+    let mut offer_continue = false;
+    let mut bot = bot_from_args(seed);
+    let profile = profile_from_args();
+    let mut daily_log: DailyLog = storage::load(&daily_key(&profile))
+        .and_then(|saved| saved.parse().ok())
+        .unwrap_or_default();
+    // Result line of finished daily challenge
+    let mut daily_share: Option<String> = None;
+    let puzzles = default_puzzles();
+    let mut solved_puzzles: SolvedPuzzles = storage::load(&puzzles_key(&profile))
+        .and_then(|saved| saved.parse().ok())
+        .unwrap_or_default();
+    // Puzzle selected in browser, while it is shown
+    let mut puzzle_browser: Option<usize> = None;
+    let mut game = if std::env::args().any(|arg| arg == "--campaign") {
+        // Continue saved campaign if any, unless it is over
+        let levels = default_levels();
+        let progress = storage::load(PROGRESS_KEY)
+            .and_then(|saved| saved.parse::<Progress>().ok())
+            .filter(|progress| progress.lives > 0 && progress.level < levels.len())
+            .unwrap_or_else(|| Progress::new(seed, 3));
+        Game::Campaign(Campaign::new(levels, progress).unwrap())
+    } else if let Some(replay) = std::env::args()
+        .any(|arg| arg == "--replay")
+        .then(|| storage::load(REPLAY_KEY))
+        .flatten()
+        .and_then(|saved| saved.parse::<Replay>().ok())
+    {
+        Game::Replay(Box::new(Playback::new(replay)))
+    } else if let Some(hot_seat) = hot_seat_from_args(seed, bot.as_ref()) {
+        Game::HotSeat(hot_seat)
+    } else if std::env::args().any(|arg| arg == "--daily") {
+        let date = Date::from_unix_time(macroquad::miniquad::date::now());
+        let ranked = daily_log.start(date);
+        storage::save(&daily_key(&profile), &daily_log.to_string());
+        Game::Daily {
+            session: Daily::new(date).session(),
+            date,
+            ranked,
         }
-        let idx = rand::gen_range(0, fillable_cells.len());
-        let cell = fillable_cells.remove(idx);
-        shuffled.push(cell);
-    }
-    mapping.fill_regions(&mut shuffled, 1..).unwrap();
+    } else if std::env::args().any(|arg| arg == "--puzzle") {
+        // Browser opens on first puzzle not solved yet
+        let first = puzzles
+            .iter()
+            .position(|puzzle| !solved_puzzles.contains(&puzzle.id))
+            .unwrap_or(0);
+        puzzle_browser = Some(first);
+        Game::Puzzle(PuzzleGame::new(puzzles[first].clone()).unwrap())
+    } else {
+        // Game left unfinished is offered before starting a new one
+        match storage::load(SAVE_KEY)
+            .and_then(|saved| GameSession::load(&saved).ok())
+            .filter(|session| !matches!(session.state(), State::Won | State::Lost))
+        {
+            Some(session) => {
+                offer_continue = true;
+                Game::Free(session)
+            }
+            None => Game::Free(GameSession::new(rules_from_args(), seed)),
+        }
+    };
+
+    let mut lifetime: LifetimeStats = storage::load(&stats_key(&profile))
+        .and_then(|saved| saved.parse().ok())
+        .unwrap_or_default();
+    let mut show_stats = false;
+    let mut achievements = AchievementTracker::new(
+        default_achievements(),
+        storage::load(&achievements_key(&profile))
+            .and_then(|saved| saved.parse().ok())
+            .unwrap_or_default(),
+    );
+    // Unlocked achievements waiting to be shown, current one first
+    let mut toasts: Vec<Achievement> = Vec::new();
+    // When current toast appeared
+    let mut toast_shown = 0.;
+    let mut leaderboards: Leaderboards = storage::load(&leaderboards_key(&profile))
+        .and_then(|saved| saved.parse().ok())
+        .unwrap_or_default();
+    let mut show_leaderboard = false;
+    // Table opened by game over rather than by player, gameplay keys still work
+    let mut leaderboard_shown_on_over = false;
+    // Rank of latest finished game in its table
+    let mut last_rank: Option<usize> = None;
+
+    // Keyboard cursor stops on empty cells with `--no-skip-empty`
+    let skip_empty = !std::env::args().any(|arg| arg == "--no-skip-empty");
+    let mut cursor: Option<Cursor> = None;
+    // Focus ring is only shown while playing with keyboard
+    let mut keyboard_focus = false;
 
     let board_drawer = BoardDrawer::new().await;
+    if let Some(url) = net::connect_url() {
+        play_online(&url, &profile, &board_drawer).await;
+        return;
+    }
+    if let Some(versus) = versus_from_args(seed) {
+        play_versus(versus, bot, &board_drawer).await;
+        return;
+    }
     let mut interaction: Interaction = Default::default();
-    let mut connector: CellConnector = CellConnector::new();
-    let mut debug_paths: Vec<Path> = Vec::new();
     loop {
         //-------------------------------------------------Check exit condition
-        if interaction.check_exit() || mapping.no_more_move() {
+        if interaction.check_exit() || is_quit_requested() {
+            if let Game::Free(session) = &game {
+                if !session.state().is_over() {
+                    storage::save(SAVE_KEY, &session.save());
+                }
+            }
             break;
         }
 
@@ -215,49 +1516,497 @@ pub async fn main() {
             width: screen_width(),
             height: screen_height(),
         };
+        let mapping = game.session().mapping();
         let board = screen.game_board_region(mapping.columns, mapping.rows);
+        let hud_buttons = screen.hud_buttons(PowerUp::ALL.len());
 
-        //-------------------------------------------Update interaction (click)
-        if let Some((x, y)) = interaction.get_click() {
-            let click_coord = Coordinate { x, y };
-            if let Ok(cell) = board.cell_from_coord(&click_coord) {
-                if NO_SPRITE != mapping.get_sprite(&cell) {
-                    connector.select(cell);
+        //-----------------------------------Update interaction (click and drag)
+        let mut input = Vec::new();
+        let gesture = interaction.get_gesture();
+        // Tile dragged from and tile under pointer
+        let mut dragging = None;
+        match &gesture {
+            Some(Gesture::Click(coord)) => {
+                if let Ok(cell) = board.cell_from_coord(coord) {
+                    input.push(Action::Select(cell));
+                } else if let Some((_, power_up)) = hud_buttons
+                    .iter()
+                    .zip(PowerUp::ALL)
+                    .find(|(button, _)| button.contain_coord(coord))
+                {
+                    input.push(Action::UsePowerUp(power_up));
+                }
+            }
+            Some(Gesture::Drag { from, to }) => {
+                if let (Ok(from), Ok(to)) = (board.cell_from_coord(from), board.cell_from_coord(to))
+                {
+                    dragging = Some((from, to));
+                }
+            }
+            Some(Gesture::Drop { from, to }) => {
+                match (board.cell_from_coord(from), board.cell_from_coord(to)) {
+                    (Ok(from), Ok(to)) if from != to => {
+                        let selection = game.session().connector().get_selections();
+                        input.extend(drop_actions(&selection, from, to));
+                    }
+                    (Ok(from), Ok(_)) => input.push(Action::Select(from)),
+                    _ => {}
                 }
             }
+            None => {}
+        }
+        if gesture.is_some() {
+            keyboard_focus = false;
         }
 
-        //-----------------------------------------Update connector and mapping
-        if let Err(mut conquered) = connector.update(&mut mapping, get_time()) {
-            debug_paths.clear();
-            debug_paths.append(&mut conquered);
+        //----------------------------------------------Update interaction (keys)
+        let mapping = game.session().mapping();
+        if let Some(direction) = interaction.get_cursor_move() {
+            let cursor = cursor.get_or_insert_with(|| Cursor::new(mapping, skip_empty));
+            cursor.clamp(mapping);
+            if keyboard_focus {
+                cursor.step(mapping, direction);
+            }
+            keyboard_focus = true;
+        }
+        if interaction.check_cursor_select() {
+            if let Some(cursor) = cursor.as_mut().filter(|_| keyboard_focus) {
+                cursor.clamp(mapping);
+                input.push(Action::Select(cursor.cell));
+            }
+        }
+        if let Some(power_up) = interaction.get_power_up_key() {
+            input.push(Action::UsePowerUp(power_up));
+        }
+        if interaction.check_hint() {
+            input.push(Action::Hint);
+        }
+        if interaction.check_undo() {
+            input.push(Action::Undo);
+        }
+        if interaction.check_pause() {
+            input.push(Action::Pause);
+        }
+        if interaction.check_shuffle() {
+            input.push(Action::Shuffle);
+        }
+        if interaction.check_stats() {
+            show_stats = !show_stats;
+        }
+        if interaction.check_leaderboard() {
+            show_leaderboard = !show_leaderboard;
+            leaderboard_shown_on_over = false;
+        }
+        // Table opened at game over goes away with next game
+        if leaderboard_shown_on_over && !game.is_over() {
+            show_leaderboard = false;
+            leaderboard_shown_on_over = false;
+        }
+        if show_leaderboard {
+            if interaction.check_export() {
+                storage::export_file(SCORES_FILE, &leaderboards.export(storage::load));
+            }
+            if interaction.check_import() {
+                storage::open_import(SCORES_FILE);
+            }
+            // Watch record, replay is kept as long as entry stays in table
+            let record = interaction
+                .get_entry_key()
+                .zip(game.mode())
+                .and_then(|(idx, mode)| {
+                    let key = TableKey::new(mode, game.session().rules());
+                    leaderboards.table(&key)?.entries.get(idx).cloned()
+                })
+                .and_then(|entry| storage::load(&entry.replay))
+                .and_then(|saved| saved.parse::<Replay>().ok());
+            if let Some(replay) = record {
+                game = Game::Replay(Box::new(Playback::new(replay)));
+                show_leaderboard = false;
+            }
+            if !leaderboard_shown_on_over {
+                input.clear();
+            }
+        }
+        if let Some(imported) = storage::poll_import() {
+            match Leaderboards::import(&imported) {
+                Ok((imported, replays)) => {
+                    for (key, replay) in replays {
+                        storage::save(&key, &replay);
+                    }
+                    for evicted in leaderboards.merge(imported) {
+                        storage::remove(&evicted.replay);
+                    }
+                    storage::save(&leaderboards_key(&profile), &leaderboards.to_string());
+                }
+                Err(e) => warn!("Cannot import high scores: {}", e),
+            }
+        }
+        if let Some(share) = daily_share.as_ref().filter(|_| interaction.check_copy()) {
+            macroquad::miniquad::window::clipboard_set(share);
+        }
+
+        if let Game::Puzzle(puzzle) = &mut game {
+            let current = puzzles
+                .iter()
+                .position(|candidate| candidate.id == puzzle.puzzle().id)
+                .unwrap_or(0);
+            if let Some(selected) = puzzle_browser {
+                let selected = match interaction.get_cursor_move() {
+                    Some(Direction::Up) => selected.saturating_sub(1),
+                    Some(Direction::Down) => (selected + 1).min(puzzles.len() - 1),
+                    _ => selected,
+                };
+                puzzle_browser = Some(selected);
+                if interaction.check_confirm() {
+                    *puzzle = PuzzleGame::new(puzzles[selected].clone()).unwrap();
+                    puzzle_browser = None;
+                    keyboard_focus = false;
+                }
+                input.clear();
+            } else if interaction.check_browse() {
+                puzzle_browser = Some(current);
+                input.clear();
+            } else if interaction.check_retry() {
+                puzzle.retry();
+                input.clear();
+            } else if puzzle.state() == PuzzleState::Solved && interaction.check_confirm() {
+                // Last puzzle goes back to browser
+                match puzzles.get(current + 1) {
+                    Some(next) => *puzzle = PuzzleGame::new(next.clone()).unwrap(),
+                    None => puzzle_browser = Some(current),
+                }
+                input.clear();
+            }
+        }
+
+        //----------------------------------------------------Update session
+        if let Game::Campaign(campaign) = &mut game {
+            // Leave level complete or failed screen, click is not a selection
+            let waiting = matches!(campaign.stage(), Stage::LevelComplete | Stage::LevelFailed);
+            if waiting && (gesture.is_some() || interaction.check_confirm()) {
+                campaign.advance();
+                input.clear();
+            }
+        }
+        if offer_continue {
+            if interaction.check_confirm() {
+                // Saved game comes back paused, this pause resumes it
+                input = vec![Action::Pause];
+                offer_continue = false;
+            } else if interaction.check_new_game() {
+                storage::remove(SAVE_KEY);
+                game = Game::Free(GameSession::new(rules_from_args(), seed));
+                input.clear();
+                offer_continue = false;
+            } else {
+                input.clear();
+            }
+        }
+        if let Game::Replay(playback) = &mut game {
+            if let Some(speed) = interaction.get_replay_speed() {
+                playback.speed = speed;
+            }
+            if let Some(delta) = interaction.get_replay_seek() {
+                playback.seek((playback.session().clock() + delta).max(0.));
+            }
+            input.clear();
+        }
+        // Last seat of hot-seat is played by bot
+        if let (Game::HotSeat(hot_seat), Some(bot)) = (&game, bot.as_mut()) {
+            if hot_seat.current() == hot_seat.players().len() - 1 {
+                input.retain(|action| *action == Action::Pause);
+                input.extend(bot.update(hot_seat.session(), get_frame_time() as f64));
+            }
+        }
+        // Puzzle waits while browser is shown
+        let events = match puzzle_browser {
+            Some(_) => Vec::new(),
+            None => game.update(&input, get_frame_time() as f64),
+        };
+        if let Game::Puzzle(puzzle) = &game {
+            if puzzle.state() == PuzzleState::Solved && solved_puzzles.insert(&puzzle.puzzle().id) {
+                storage::save(&puzzles_key(&profile), &solved_puzzles.to_string());
+            }
+        }
+        // Add finished game to lifetime stats of profile and keep its replay
+        // Watching a replay doesn't earn anything, puzzles keep their own
+        // record
+        if !matches!(game, Game::Replay(_) | Game::Puzzle(_)) {
+            let unlocked: Vec<Achievement> = events
+                .iter()
+                .flat_map(|event| achievements.record(event))
+                .collect();
+            if !unlocked.is_empty() {
+                storage::save(
+                    &achievements_key(&profile),
+                    &achievements.unlocked.to_string(),
+                );
+                if toasts.is_empty() {
+                    toast_shown = get_time();
+                }
+                toasts.extend(unlocked);
+            }
+        }
+        let finished = events
+            .iter()
+            .any(|event| matches!(event, Event::StateChanged(State::Won | State::Lost)));
+        if finished && matches!(game, Game::Free(_) | Game::Campaign(_) | Game::Daily { .. }) {
+            lifetime.add(game.session().stats());
+            storage::save(&stats_key(&profile), &lifetime.to_string());
+            storage::save(REPLAY_KEY, &game.session().replay().to_string());
+        }
+        if let (true, Some(mode)) = (finished, game.mode()) {
+            let session = game.session();
+            let key = TableKey::new(mode, session.rules());
+            let today = Date::from_unix_time(macroquad::miniquad::date::now());
+            let mut entry = Entry::new(&profile, today, session);
+            leaderboards.unique_replay(&mut entry);
+            let replay = entry.replay.clone();
+            storage::save(&replay, &session.replay().to_string());
+            let (rank, evicted) = leaderboards.insert(&key, entry);
+            if rank.is_none() {
+                storage::remove(&replay);
+            }
+            if let Some(evicted) = evicted.filter(|evicted| evicted.replay != replay) {
+                storage::remove(&evicted.replay);
+            }
+            storage::save(&leaderboards_key(&profile), &leaderboards.to_string());
+            last_rank = rank;
+            if game.is_over() {
+                show_leaderboard = true;
+                leaderboard_shown_on_over = true;
+            }
+        }
+        if let (
+            true,
+            Game::Daily {
+                session,
+                date,
+                ranked,
+            },
+        ) = (finished, &game)
+        {
+            let result = DailyResult::new(*date, session);
+            // Practice runs never replace ranked result
+            if *ranked && daily_log.finish(result.clone()) {
+                storage::save(&daily_key(&profile), &daily_log.to_string());
+            }
+            info!("{}", result.share());
+            daily_share = Some(result.share());
+        }
+        // Keep free game after each change of board, web build has no quit
+        // event to save on
+        if let Game::Free(session) = &game {
+            if matches!(session.state(), State::Won | State::Lost) {
+                if events
+                    .iter()
+                    .any(|event| matches!(event, Event::StateChanged(_)))
+                {
+                    storage::remove(SAVE_KEY);
+                }
+            } else if events
+                .iter()
+                .any(|event| matches!(event, Event::Matched(_) | Event::Shuffled))
+            {
+                storage::save(SAVE_KEY, &session.save());
+            }
         }
 
         //---------------------------------------------------------------Render
         // clear screen
         clear_background(BLANK);
-        // Draw current board after update
-        board_drawer.draw_board(&board, &mapping);
+        if let Some(selected) = puzzle_browser {
+            board_drawer.draw_puzzle_browser(&puzzles, &solved_puzzles, selected);
+            next_frame().await;
+            continue;
+        }
+        let session = game.session();
+        match session.state() {
+            State::Loading => {
+                Drawer::draw_loading_screen();
+                next_frame().await;
+                continue;
+            }
+            State::Paused => {
+                Drawer::draw_message(if offer_continue {
+                    CONTINUE_MESSAGE
+                } else {
+                    "Paused"
+                });
+                next_frame().await;
+                continue;
+            }
+            _ => {}
+        }
 
-        // Draw border for current selection
-        if let Some(&cell) = connector.get_selection() {
-            board_drawer.draw_select_border(&board, &cell)
+        let mapping = session.mapping();
+        let connector = session.connector();
+        board_drawer.draw_session(&board, session);
+
+        // Draw keyboard cursor
+        if let Some(cursor) = cursor.as_ref().filter(|_| keyboard_focus) {
+            board_drawer.draw_focus_ring(&board, &cursor.cell);
         }
 
-        // Draw pending destroy couples
-        for couple in connector
-            .poll_destroying()
-            .iter()
-            .filter(|couple| get_time() - couple.epoch < 0.5)
+        // Draw live preview of dragging
+        if let Some((from, to)) = dragging {
+            if mapping.get_sprite(&to) != NO_SPRITE && mapping.get_sprite(&from) != NO_SPRITE {
+                let mut cells: Vec<Cell> = match connector.get_selection() {
+                    Some(&last) if last == from => {
+                        connector.get_selections().into_iter().copied().collect()
+                    }
+                    _ => vec![from],
+                };
+                cells.push(to);
+                match session.visibility() {
+                    // Don't spoil face-down tiles
+                    Some(_) => board_drawer.draw_hover_border(&board, &to, YELLOW),
+                    None => match connector.preview(mapping, &cells) {
+                        Ok(nodes) => {
+                            board_drawer.draw_hover_border(&board, &to, GREEN);
+                            Drawer::draw_connector(&board, &nodes, GREEN);
+                        }
+                        Err(_) => board_drawer.draw_hover_border(&board, &to, RED),
+                    },
+                }
+            }
+        }
+
+        // Draw HUD
+        Drawer::draw_score(session.scoring());
+        if let (Some(time_left), Some(time_limit)) =
+            (session.time_left(), session.rules().time_limit)
         {
-            for (cell, sprite_id) in couple.remnants.iter() {
-                board_drawer.draw_sprite(&board, &cell, *sprite_id);
+            Drawer::draw_timer_bar(time_left, time_limit, session.is_frozen());
+        }
+        Drawer::draw_power_ups(
+            &hud_buttons,
+            session.inventory(),
+            connector.is_ignoring_path(),
+        );
+
+        // Draw result when game or level is over
+        match &game {
+            Game::Campaign(campaign) => {
+                Drawer::draw_campaign_status(campaign);
+                match campaign.stage() {
+                    Stage::LevelComplete => Drawer::draw_message(&format!(
+                        "Level complete! Score: {}, click to continue",
+                        campaign.total_score()
+                    )),
+                    Stage::LevelFailed => Drawer::draw_message(&format!(
+                        "Level failed, {} lives left, click to retry",
+                        campaign.progress().lives
+                    )),
+                    Stage::GameOver => Drawer::draw_message("Game over"),
+                    Stage::Finished => Drawer::draw_message(&format!(
+                        "Campaign finished! Score: {}",
+                        campaign.total_score()
+                    )),
+                    Stage::Playing => {
+                        if session.state() == State::Stuck {
+                            Drawer::draw_message("No more moves, press R to shuffle")
+                        }
+                    }
+                }
+            }
+            Game::Replay(playback) => {
+                let status = format!(
+                    "Replay {:.0}/{:.0}s  {:?}  1/2/3: speed, arrows: seek",
+                    session.clock(),
+                    playback.replay().duration,
+                    playback.speed
+                );
+                draw_text(&status, 10., screen_height() - 16., 24., WHITE);
+                if playback.desync().is_some() {
+                    draw_text(
+                        "Replay no longer matches recording",
+                        10.,
+                        screen_height() - 44.,
+                        24.,
+                        ORANGE,
+                    );
+                }
+                if playback.is_finished() {
+                    Drawer::draw_message("End of replay");
+                }
             }
-            Drawer::draw_connector(&board, &couple.nodes);
+            Game::HotSeat(hot_seat) => {
+                Drawer::draw_players(hot_seat);
+                if matches!(session.state(), State::Won | State::Lost) {
+                    match hot_seat.leaders()[..] {
+                        [winner] => Drawer::draw_message(&format!(
+                            "{} wins!",
+                            hot_seat.players()[winner].name
+                        )),
+                        _ => Drawer::draw_message("Draw!"),
+                    }
+                } else if session.state() == State::Stuck {
+                    Drawer::draw_message("No more moves, press R to shuffle")
+                }
+            }
+            Game::Daily { date, ranked, .. } => {
+                let status = match (ranked, daily_log.ranked(*date)) {
+                    (false, Some(result)) => {
+                        format!("Daily {} practice, ranked: {}", date, result.share())
+                    }
+                    _ => format!("Daily {} ranked attempt", date),
+                };
+                draw_text(&status, 10., screen_height() - 16., 24., WHITE);
+                match (session.state(), daily_share.as_ref()) {
+                    (State::Won | State::Lost, Some(share)) => {
+                        Drawer::draw_message(share);
+                        draw_text(
+                            "Press C to copy result",
+                            10.,
+                            screen_height() - 44.,
+                            24.,
+                            GOLD,
+                        );
+                    }
+                    (State::Stuck, _) => Drawer::draw_message("No more moves, press R to shuffle"),
+                    _ => {}
+                }
+            }
+            Game::Puzzle(puzzle) => {
+                board_drawer.draw_puzzle_status(puzzle);
+                match puzzle.state() {
+                    PuzzleState::Solved => {
+                        Drawer::draw_message("Puzzle solved! Enter: next puzzle")
+                    }
+                    PuzzleState::Broken(_) => Drawer::draw_message("Constraint broken, R: retry"),
+                    PuzzleState::Stuck => Drawer::draw_message("No more moves, R: retry"),
+                    PuzzleState::Playing => {}
+                }
+            }
+            Game::Free(_) => match session.state() {
+                State::Won => Drawer::draw_message("Board cleared!"),
+                State::Lost => Drawer::draw_message("Time is up"),
+                State::Stuck => Drawer::draw_message("No more moves, press R to shuffle"),
+                _ => {}
+            },
         }
 
-        // Draw debug points
-        Drawer::draw_conquered(&board, &debug_paths);
+        if offer_continue {
+            Drawer::draw_message(CONTINUE_MESSAGE);
+        }
+        if show_stats {
+            Drawer::draw_stats(&profile, &lifetime, session.stats());
+        }
+        if let (true, Some(mode)) = (show_leaderboard, game.mode()) {
+            let key = TableKey::new(mode, session.rules());
+            let highlight = last_rank.filter(|_| session.state().is_over());
+            Drawer::draw_leaderboard(&key, leaderboards.table(&key), highlight);
+        }
+        if let Some(achievement) = toasts.first() {
+            let age = get_time() - toast_shown;
+            if age < TOAST_DURATION {
+                Drawer::draw_toast(achievement, age);
+            } else {
+                toasts.remove(0);
+                toast_shown = get_time();
+            }
+        }
 
         //--------------------------------------------------Wait for next frame
         next_frame().await;
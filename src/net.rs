@@ -0,0 +1,130 @@
+//! WebSocket connection to versus server. Native build uses `tungstenite`
+//! over a non-blocking socket, web build uses browser WebSocket through the
+//! `meme_socket` plugin in `index.html`.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{io::ErrorKind, net::TcpStream};
+    use tungstenite::{stream::MaybeTlsStream, Error, Message, WebSocket};
+
+    pub struct Connection {
+        socket: WebSocket<MaybeTlsStream<TcpStream>>,
+        closed: bool,
+    }
+
+    impl Connection {
+        pub fn connect(url: &str) -> Result<Self, String> {
+            let (socket, _) = tungstenite::connect(url).map_err(|e| e.to_string())?;
+            if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+                stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+            }
+            Ok(Self {
+                socket,
+                closed: false,
+            })
+        }
+
+        pub fn send(&mut self, message: &str) {
+            match self.socket.send(Message::Text(message.to_string())) {
+                Err(Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => self.closed = true,
+                Ok(_) => {}
+            }
+        }
+
+        /// Next message if any arrived, never blocks
+        pub fn receive(&mut self) -> Option<String> {
+            loop {
+                match self.socket.read() {
+                    Ok(Message::Text(text)) => return Some(text),
+                    Ok(Message::Close(_)) => self.closed = true,
+                    Ok(_) => continue,
+                    Err(Error::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(_) => self.closed = true,
+                }
+                return None;
+            }
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.closed
+        }
+    }
+
+    /// Server address from `--connect <url>`
+    pub fn connect_url() -> Option<String> {
+        let args: Vec<String> = std::env::args().collect();
+        args.iter()
+            .position(|arg| arg == "--connect")
+            .and_then(|idx| args.get(idx + 1))
+            .cloned()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    extern "C" {
+        fn socket_open(url: *const u8, url_len: usize);
+        fn socket_send(message: *const u8, message_len: usize);
+        /// Length of next message, -1 when none, -2 when closed
+        fn socket_next_len() -> i32;
+        fn socket_next(buf: *mut u8);
+        /// Length of `connect` page parameter, -1 when absent
+        fn socket_url_len() -> i32;
+        fn socket_url(buf: *mut u8);
+    }
+
+    /// Browser keeps a single socket
+    pub struct Connection {
+        closed: bool,
+    }
+
+    impl Connection {
+        pub fn connect(url: &str) -> Result<Self, String> {
+            unsafe { socket_open(url.as_ptr(), url.len()) };
+            Ok(Self { closed: false })
+        }
+
+        /// Queued by browser until socket is open
+        pub fn send(&mut self, message: &str) {
+            unsafe { socket_send(message.as_ptr(), message.len()) }
+        }
+
+        /// Next message if any arrived, never blocks
+        pub fn receive(&mut self) -> Option<String> {
+            unsafe {
+                match socket_next_len() {
+                    -2 => {
+                        self.closed = true;
+                        None
+                    }
+                    len if len < 0 => None,
+                    len => {
+                        let mut buf = vec![0u8; len as usize];
+                        socket_next(buf.as_mut_ptr());
+                        String::from_utf8(buf).ok()
+                    }
+                }
+            }
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.closed
+        }
+    }
+
+    /// Server address from `?connect=<url>` page parameter
+    pub fn connect_url() -> Option<String> {
+        unsafe {
+            let len = socket_url_len();
+            if len < 0 {
+                return None;
+            }
+            let mut buf = vec![0u8; len as usize];
+            socket_url(buf.as_mut_ptr());
+            String::from_utf8(buf).ok()
+        }
+    }
+}
+
+pub use backend::*;
@@ -0,0 +1,109 @@
+//! Key-value storage for saved data. Native build keeps one file per key in
+//! `saves` directory, web build uses browser local storage through the
+//! `meme_storage` plugin in `index.html`. Files shared with other players are
+//! exported to working directory on native build and downloaded on web build.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::{fs, path::PathBuf, sync::Mutex};
+
+    const SAVE_DIR: &str = "saves";
+
+    fn path(key: &str) -> PathBuf {
+        PathBuf::from(SAVE_DIR).join(format!("{}.txt", key))
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        fs::read_to_string(path(key)).ok()
+    }
+
+    pub fn save(key: &str, value: &str) {
+        if let Err(e) = fs::create_dir_all(SAVE_DIR).and_then(|_| fs::write(path(key), value)) {
+            eprintln!("Cannot save {}: {}", key, e);
+        }
+    }
+
+    pub fn remove(key: &str) {
+        let _ = fs::remove_file(path(key));
+    }
+
+    /// Content of file opened by `open_import`, until taken
+    static IMPORTED: Mutex<Option<String>> = Mutex::new(None);
+
+    pub fn export_file(name: &str, content: &str) {
+        match fs::write(name, content) {
+            Ok(_) => println!("Exported {}", name),
+            Err(e) => eprintln!("Cannot export {}: {}", name, e),
+        }
+    }
+
+    /// Read file `name`, content comes from `poll_import`
+    pub fn open_import(name: &str) {
+        match fs::read_to_string(name) {
+            Ok(content) => *IMPORTED.lock().unwrap() = Some(content),
+            Err(e) => eprintln!("Cannot import {}: {}", name, e),
+        }
+    }
+
+    pub fn poll_import() -> Option<String> {
+        IMPORTED.lock().unwrap().take()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    extern "C" {
+        fn storage_len(key: *const u8, key_len: usize) -> i32;
+        fn storage_get(key: *const u8, key_len: usize, buf: *mut u8);
+        fn storage_set(key: *const u8, key_len: usize, value: *const u8, value_len: usize);
+        fn storage_remove(key: *const u8, key_len: usize);
+        fn storage_download(name: *const u8, name_len: usize, value: *const u8, value_len: usize);
+        fn storage_pick_file();
+        /// Length of picked file, -1 until a file is picked
+        fn storage_picked_len() -> i32;
+        fn storage_picked(buf: *mut u8);
+    }
+
+    pub fn load(key: &str) -> Option<String> {
+        unsafe {
+            let len = storage_len(key.as_ptr(), key.len());
+            if len < 0 {
+                return None;
+            }
+            let mut buf = vec![0u8; len as usize];
+            storage_get(key.as_ptr(), key.len(), buf.as_mut_ptr());
+            String::from_utf8(buf).ok()
+        }
+    }
+
+    pub fn save(key: &str, value: &str) {
+        unsafe { storage_set(key.as_ptr(), key.len(), value.as_ptr(), value.len()) }
+    }
+
+    pub fn remove(key: &str) {
+        unsafe { storage_remove(key.as_ptr(), key.len()) }
+    }
+
+    pub fn export_file(name: &str, content: &str) {
+        unsafe { storage_download(name.as_ptr(), name.len(), content.as_ptr(), content.len()) }
+    }
+
+    /// Let player pick a file, content comes from `poll_import` once read
+    pub fn open_import(_name: &str) {
+        unsafe { storage_pick_file() }
+    }
+
+    pub fn poll_import() -> Option<String> {
+        unsafe {
+            let len = storage_picked_len();
+            if len < 0 {
+                return None;
+            }
+            let mut buf = vec![0u8; len as usize];
+            storage_picked(buf.as_mut_ptr());
+            String::from_utf8(buf).ok()
+        }
+    }
+}
+
+pub use backend::*;
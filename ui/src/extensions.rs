@@ -4,12 +4,15 @@ use ::board::data_type::cell::Cell;
 impl Size {
     /// Fit game board in screen
     pub fn game_board_region(&self, columns: usize, rows: usize) -> Region {
-        // TODO: Layout game board based on screen orientation
-        let x = self.width * 0.1;
-        let y = self.height * 0.1;
-        let board_coord = Coordinate { x, y };
+        self.game_board_regions(1, columns, rows).remove(0)
+    }
 
-        let width = self.width * 0.8;
+    /// Fit `count` game boards side by side in screen, from left to right
+    pub fn game_board_regions(&self, count: usize, columns: usize, rows: usize) -> Vec<Region> {
+        // TODO: Layout game board based on screen orientation
+        let count = count.max(1);
+        let gap = self.width * 0.02;
+        let width = (self.width * 0.8 - gap * (count - 1) as f32) / count as f32;
         let height = self.height * 0.8;
         let board_size = Size { width, height };
 
@@ -21,11 +24,37 @@ impl Size {
             height: cell_height,
         };
 
-        Region {
-            coord: board_coord,
-            size: board_size,
-            meta: Meta::BOARD(cell_size),
-        }
+        (0..count)
+            .map(|idx| Region {
+                coord: Coordinate {
+                    x: self.width * 0.1 + idx as f32 * (width + gap),
+                    y: self.height * 0.1,
+                },
+                size: board_size,
+                meta: Meta::BOARD(cell_size),
+            })
+            .collect()
+    }
+}
+
+impl Size {
+    /// Column of square buttons on right margin, next to game board
+    pub fn hud_buttons(&self, count: usize) -> Vec<Region> {
+        let side = (self.width * 0.08).min(self.height * 0.8 / count.max(1) as f32);
+        let x = self.width * 0.91;
+        (0..count)
+            .map(|idx| Region {
+                coord: Coordinate {
+                    x,
+                    y: self.height * 0.1 + idx as f32 * side,
+                },
+                size: Size {
+                    width: side,
+                    height: side,
+                },
+                meta: Meta::WIDGET(idx),
+            })
+            .collect()
     }
 }
 
@@ -48,7 +77,7 @@ impl Region {
     /// Map from coordinate inside region to board column and row
     pub fn cell_from_coord(&self, coord: &Coordinate) -> Result<Cell, &'static str> {
         if let Meta::BOARD(cell_size) = self.meta {
-            if !self.contain_coord(&coord) {
+            if !self.contain_coord(coord) {
                 return Err("Coord outside board region");
             }
             // board has invisible border, so cell's column and row starts at 1
@@ -20,6 +20,8 @@ pub enum Meta {
     BOARD(Size),
     /// Cell at specific column and row
     CELL(usize, usize),
+    /// HUD widget at specific index
+    WIDGET(usize),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]